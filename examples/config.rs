@@ -1,8 +1,8 @@
 use anyhow::Result;
-use xdiff::{RequestConfig, LoadConfig};
+use xdiff::{LoadConfig, RequestConfig};
 fn main() -> Result<()> {
     let content = include_str!("../fixtures/xreq_test.yml");
     let config = RequestConfig::from_yaml(content)?;
     println!("{:#?}", config);
     Ok(())
-}
\ No newline at end of file
+}
@@ -49,4 +49,4 @@ fn main() {
             }
         }
     }
-}
\ No newline at end of file
+}
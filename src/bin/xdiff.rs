@@ -1,38 +1,51 @@
 use anyhow::{anyhow, Result};
 use clap::Parser;
 use dialoguer::{theme::ColorfulTheme, Input, MultiSelect};
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 use xdiff::{
-    cli::{Action, Args, RunArgs},
-    highlight_text, DiffConfig, DiffProfile, ExtraArgs, LoadConfig, RequestProfile,
-    ResponseProfile, process_error_output,
+    cli::{
+        CookiesAction, CookiesArgs, DiffAction, DiffArgs, DiffImportArgs, DiffRunArgs, ExportArgs,
+    },
+    highlight_text, import_requests, process_error_output, rebase, summarize_rows, Dataset,
+    DiffConfig, DiffProfile, ExtraArgs, LoadConfig, RequestProfile, ResponseProfile,
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = DiffArgs::parse();
 
     let result = match args.action {
-        Action::Run(args) => run(args).await,
-        Action::Parse => parse().await,
+        DiffAction::Run(args) => run(args).await,
+        DiffAction::Parse => parse().await,
+        DiffAction::Cookies(args) => cookies(args).await,
+        DiffAction::Import(args) => import(args).await,
+        DiffAction::Export(args) => export(args).await,
         _ => panic!("Unknown action"),
     };
     process_error_output(result)?;
     Ok(())
 }
 
-async fn run(args: RunArgs) -> Result<()> {
-    let config_file = args.config.unwrap_or_else(|| "./xdiff.yml".to_string());
+async fn run(args: DiffRunArgs) -> Result<()> {
+    let run = args.run;
+    let config_file = run.config.unwrap_or_else(|| "./xdiff.yml".to_string());
     let config = DiffConfig::load_yaml(&config_file).await?;
-    let profile = config.get_profile(&args.profile).ok_or_else(|| {
+    let profile = config.get_profile(&run.profile).ok_or_else(|| {
         anyhow!(
             "Profile {} not found in config file {}",
-            args.profile,
+            run.profile,
             config_file
         )
     })?;
-    let extra_args = args.extra_params.into();
-    let output = profile.diff(extra_args).await?;
+    let extra_args: ExtraArgs = run.extra_params.try_into()?;
+    let output = match args.dataset {
+        Some(path) => {
+            let dataset = Dataset::load(&path).await?;
+            let results = dataset.run(profile, &extra_args, args.concurrency).await;
+            summarize_rows(&results, args.show_diffs)?
+        }
+        None => profile.diff(extra_args).await?,
+    };
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
     write!(&mut stdout, "---\n{}", output)?;
@@ -69,14 +82,105 @@ async fn parse() -> Result<()> {
     let profile = DiffProfile::new(req1, req2, res);
     let config = DiffConfig::new(vec![(name, profile)].into_iter().collect());
 
-    let result = serde_yaml::to_string(&config)?;
+    print_config(&serde_yaml::to_string(&config)?)
+}
+
+async fn export(args: ExportArgs) -> Result<()> {
+    let config_file = args.config.unwrap_or_else(|| "./xdiff.yml".to_string());
+    let config = DiffConfig::load_yaml(&config_file).await?;
+    let profile = config.get_profile(&args.profile).ok_or_else(|| {
+        anyhow!(
+            "Profile {} not found in config file {}",
+            args.profile,
+            config_file
+        )
+    })?;
+    let extra_args: ExtraArgs = args.extra_params.try_into()?;
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    for (side, req) in [("req1", &profile.req1), ("req2", &profile.req2)] {
+        let vars = req.run_setup().await?;
+        let req = req.render(&vars)?;
+        let output = req
+            .export(&extra_args, args.format, args.show_secrets)
+            .await?;
+        writeln!(&mut stdout, "# {}\n{}", side, output)?;
+    }
+    Ok(())
+}
+
+async fn import(args: DiffImportArgs) -> Result<()> {
+    let imported = import_requests(&args.source).await?;
+    for warning in imported.warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    let mut profiles = HashMap::new();
+    for req in imported.requests {
+        let req2 = match (req.profile2, args.base2.as_ref()) {
+            (_, Some(base2)) => rebase(&req.profile, base2)?,
+            (Some(req2), None) => req2,
+            (None, None) => {
+                return Err(anyhow!(
+                    "--base2 is required to import into xdiff, or --server2 for a spec"
+                ))
+            }
+        };
+        let profile = DiffProfile::new(req.profile, req2, ResponseProfile::default());
+        profiles.insert(req.name, profile);
+    }
+    let mut config = DiffConfig::new(profiles);
+    config.defaults.vars = imported.vars;
+    print_config(&serde_yaml::to_string(&config)?)
+}
+
+fn print_config(result: &str) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
 
     if atty::is(atty::Stream::Stdout) {
-        let result = highlight_text(result.as_str(), "yaml", None)?;
+        writeln!(&mut stdout, "{}", highlight_text(result, "yaml", None)?)?;
+    } else {
+        writeln!(&mut stdout, "{}", result)?;
     }
+    Ok(())
+}
+
+async fn cookies(args: CookiesArgs) -> Result<()> {
+    let config_file = args.config.unwrap_or_else(|| "./xdiff.yml".to_string());
+    let config = DiffConfig::load_yaml(&config_file).await?;
+    let profile = config.get_profile(&args.profile).ok_or_else(|| {
+        anyhow!(
+            "Profile {} not found in config file {}",
+            args.profile,
+            config_file
+        )
+    })?;
 
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
-    writeln!(&mut stdout, "{}", result)?;
+    // each side keeps its own jar
+    for (side, req) in [("req1", &profile.req1), ("req2", &profile.req2)] {
+        let jar = match req.load_cookie_jar().await? {
+            Some(jar) => jar,
+            None => {
+                writeln!(&mut stdout, "{}: no cookie_jar", side)?;
+                continue;
+            }
+        };
+        match args.action {
+            CookiesAction::Show => write!(
+                &mut stdout,
+                "{} ({}):\n{}",
+                side,
+                jar.path().display(),
+                jar.get_text()?
+            )?,
+            CookiesAction::Clear => {
+                jar.clear().await?;
+                writeln!(&mut stdout, "{}: cleared {}", side, jar.path().display())?;
+            }
+        }
+    }
     Ok(())
 }
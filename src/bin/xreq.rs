@@ -1,11 +1,12 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use dialoguer::{theme::ColorfulTheme, Input, MultiSelect};
-use std::{fmt::Write as _, io::Write as _, os::windows::process};
+use dialoguer::{theme::ColorfulTheme, Input};
+use std::{fmt::Write as _, io::Write as _};
 use xdiff::{
-    cli::{Action, Args, RunArgs},
-    get_body_text, get_header_text, get_status_text, highlight_text, ExtraArgs, LoadConfig,
-    RequestConfig, RequestProfile, process_error_output,
+    cli::{Action, Args, CookiesAction, CookiesArgs, ExportArgs, ImportArgs, RequestRunArgs},
+    diff_text, get_body_text, get_header_text, get_redirect_text, get_status_text, highlight_text,
+    import_requests, process_error_output, ExtraArgs, LoadConfig, RequestConfig, RequestProfile,
+    ResponseProfile,
 };
 
 #[tokio::main]
@@ -15,13 +16,21 @@ async fn main() -> Result<()> {
     let result = match args.action {
         Action::Run(args) => run(args).await,
         Action::Parse => parse().await,
+        Action::Cookies(args) => cookies(args).await,
+        Action::Import(args) => import(args).await,
+        Action::Export(args) => export(args).await,
         _ => panic!("Unknown action"),
     };
     process_error_output(result)?;
     Ok(())
 }
 
-async fn run(args: RunArgs) -> Result<()> {
+async fn run(args: RequestRunArgs) -> Result<()> {
+    let snapshot_dir = args.snapshot_dir;
+    let mut snapshot = ResponseProfile::snapshot();
+    let skip_headers = args.skip_header.iter().map(|h| h.to_lowercase());
+    snapshot.skip_headers.extend(skip_headers);
+    let args = args.run;
     let config_file = args.config.unwrap_or_else(|| "./xreq.yml".to_string());
     let config = RequestConfig::load_yaml(&config_file).await?;
     let profile = config.get_profile(&args.profile).ok_or_else(|| {
@@ -31,11 +40,43 @@ async fn run(args: RunArgs) -> Result<()> {
             config_file
         )
     })?;
-    let extra_args = args.extra_params.into();
+    let extra_args: ExtraArgs = args.extra_params.try_into()?;
 
+    let vars = profile.run_setup().await?;
+    let profile = profile.render(&vars)?;
     let url = profile.get_url(&extra_args)?;
 
-    let res = profile.send(&extra_args).await?.into_inner();
+    let res = profile.send(&extra_args).await?;
+    if let Some(dir) = snapshot_dir {
+        let path = dir.join(format!("{}.txt", args.profile));
+        let text = res.get_text(&snapshot).await?;
+        let recorded = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read snapshot {}", path.display()))?;
+        let snapshot = snapshot.skip_recorded_headers(&recorded);
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        if text == snapshot {
+            writeln!(
+                &mut stdout,
+                "---\nUrl: {}\n\nmatches {}",
+                url,
+                path.display()
+            )?;
+        } else {
+            write!(
+                &mut stdout,
+                "---\nUrl: {}\n\n{}",
+                url,
+                diff_text(&snapshot, &text)?
+            )?;
+        }
+        return Ok(());
+    }
+    let attempts = res.attempts();
+    let redirects = get_redirect_text(res.redirects())?;
+    let encoding = res.encoding().cloned();
+    let res = res.into_inner();
     let status = get_status_text(&res)?;
     let headers = get_header_text(&res, &[])?;
     let body = get_body_text(res, &[]).await?;
@@ -43,6 +84,15 @@ async fn run(args: RunArgs) -> Result<()> {
     let mut output = String::new();
 
     writeln!(&mut output, "Url: {}\n", url)?;
+    if attempts > 1 {
+        writeln!(&mut output, "Attempts: {}\n", attempts)?;
+    }
+    if let Some(encoding) = encoding {
+        writeln!(&mut output, "Encoding: {}\n", encoding)?;
+    }
+    if !redirects.is_empty() {
+        writeln!(&mut output, "{}", redirects)?;
+    }
     if atty::is(atty::Stream::Stdout) {
         writeln!(&mut output, "{}", status)?;
         writeln!(
@@ -77,19 +127,86 @@ async fn parse() -> Result<()> {
         .interact_text()?;
 
     let config = RequestConfig::new(vec![(name, profile)].into_iter().collect());
-    let result = serde_yaml::to_string(&config)?;
+    print_config(&serde_yaml::to_string(&config)?)
+}
+
+async fn export(args: ExportArgs) -> Result<()> {
+    let config_file = args.config.unwrap_or_else(|| "./xreq.yml".to_string());
+    let config = RequestConfig::load_yaml(&config_file).await?;
+    let profile = config.get_profile(&args.profile).ok_or_else(|| {
+        anyhow!(
+            "Profile {} not found in config file {}",
+            args.profile,
+            config_file
+        )
+    })?;
+    let extra_args: ExtraArgs = args.extra_params.try_into()?;
+
+    let vars = profile.run_setup().await?;
+    let profile = profile.render(&vars)?;
+    let output = profile
+        .export(&extra_args, args.format, args.show_secrets)
+        .await?;
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(&mut stdout, "{}", output)?;
+    Ok(())
+}
+
+async fn import(args: ImportArgs) -> Result<()> {
+    let imported = import_requests(&args.source).await?;
+    if imported.requests.iter().any(|req| req.profile2.is_some()) {
+        return Err(anyhow!("--server2 is only supported by xdiff"));
+    }
+    for warning in imported.warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    let profiles = imported
+        .requests
+        .into_iter()
+        .map(|req| (req.name, req.profile))
+        .collect();
+    let mut config = RequestConfig::new(profiles);
+    config.defaults.vars = imported.vars;
+    print_config(&serde_yaml::to_string(&config)?)
+}
 
+fn print_config(result: &str) -> Result<()> {
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
 
     if atty::is(atty::Stream::Stdout) {
-        writeln!(
-            &mut stdout,
-            "{}",
-            highlight_text(result.as_str(), "yaml", None)?
-        )?;
+        writeln!(&mut stdout, "{}", highlight_text(result, "yaml", None)?)?;
     } else {
-        writeln!(&mut stdout, "{}", result.as_str())?;
+        writeln!(&mut stdout, "{}", result)?;
+    }
+    Ok(())
+}
+
+async fn cookies(args: CookiesArgs) -> Result<()> {
+    let config_file = args.config.unwrap_or_else(|| "./xreq.yml".to_string());
+    let config = RequestConfig::load_yaml(&config_file).await?;
+    let profile = config.get_profile(&args.profile).ok_or_else(|| {
+        anyhow!(
+            "Profile {} not found in config file {}",
+            args.profile,
+            config_file
+        )
+    })?;
+    let jar = profile
+        .load_cookie_jar()
+        .await?
+        .ok_or_else(|| anyhow!("Profile {} has no cookie_jar", args.profile))?;
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    match args.action {
+        CookiesAction::Show => write!(&mut stdout, "{}", jar.get_text()?)?,
+        CookiesAction::Clear => {
+            jar.clear().await?;
+            writeln!(&mut stdout, "Cleared {}", jar.path().display())?;
+        }
     }
     Ok(())
 }
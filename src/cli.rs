@@ -1,10 +1,11 @@
 use anyhow::{anyhow, Result};
 
-use clap::{Parser, Subcommand};
-use crate::ExtraArgs;
+use crate::{config::parse_value, ExtraArgs};
+use clap::{Parser, Subcommand, ValueEnum};
+use reqwest::Url;
+use std::{io::Read, path::PathBuf};
 
-
-/// Diff two http requests and compare the difference between the response
+/// Send the http request of a profile and print the response
 #[derive(Parser, Debug)]
 #[clap(version, author, about, long_about = None)]
 pub struct Args {
@@ -15,10 +16,39 @@ pub struct Args {
 #[derive(Subcommand, Debug, Clone)]
 #[non_exhaustive]
 pub enum Action {
+    /// Send the request of a profile
+    Run(RequestRunArgs),
+    /// Parse URLS to generate a Profile
+    Parse,
+    /// Show or clear the cookies stored for a profile
+    Cookies(CookiesArgs),
+    /// Import requests from another tool and print them as a config
+    Import(ImportArgs),
+    /// Print the request of a profile as a command to run elsewhere
+    Export(ExportArgs),
+}
+
+/// Diff two http requests and compare the difference between the response
+#[derive(Parser, Debug)]
+#[clap(version, author, about, long_about = None)]
+pub struct DiffArgs {
+    #[clap(subcommand)]
+    pub action: DiffAction,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+#[non_exhaustive]
+pub enum DiffAction {
     /// Diff two Api response based on given profile
-    Run(RunArgs),
+    Run(DiffRunArgs),
     /// Parse URLS to generate a Profile
     Parse,
+    /// Show or clear the cookies stored for a profile
+    Cookies(CookiesArgs),
+    /// Import requests from another tool and print them as a config
+    Import(DiffImportArgs),
+    /// Print the request of a profile as a command to run elsewhere
+    Export(ExportArgs),
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -34,6 +64,10 @@ pub struct RunArgs {
     /// for headers, use `-e %key:value`.
     ///
     /// for body, use `-e @key=value`.
+    ///
+    /// for path params, use `-e :key=value`.
+    ///
+    /// for a raw body read from stdin, use `-e @-`.
     #[clap(short, value_parser = parse_key_val, number_of_values = 1)]
     pub extra_params: Vec<KeyVal>,
 
@@ -42,11 +76,194 @@ pub struct RunArgs {
     pub config: Option<String>,
 }
 
+#[derive(Parser, Debug, Clone)]
+pub struct DiffRunArgs {
+    #[clap(flatten)]
+    pub run: RunArgs,
+
+    /// Run once for each row of a CSV or JSON file, its columns are named like the `-e` keys
+    #[clap(long, value_parser)]
+    pub dataset: Option<PathBuf>,
+
+    /// How many dataset rows run at the same time
+    #[clap(long, value_parser, default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Print the diff of every dataset row that differs
+    #[clap(long)]
+    pub show_diffs: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct RequestRunArgs {
+    #[clap(flatten)]
+    pub run: RunArgs,
+
+    /// Diff the response with the `(profile).txt` recorded in this directory, like the
+    /// snapshots of `import har --snapshot-dir`
+    #[clap(long, value_parser)]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// Leave this header out of the snapshot diff as well, could be given several times
+    #[clap(long, value_parser, requires = "snapshot_dir")]
+    pub skip_header: Vec<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CookiesArgs {
+    /// What to do with the stored cookies
+    #[clap(value_enum)]
+    pub action: CookiesAction,
+
+    /// Profile name
+    #[clap(short, long, value_parser)]
+    pub profile: String,
+
+    /// Configuration to use.
+    #[clap(short, long, value_parser)]
+    pub config: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExportArgs {
+    /// Profile name
+    #[clap(short, long, value_parser)]
+    pub profile: String,
+
+    /// Override args, the same as for `run`
+    #[clap(short, value_parser = parse_key_val, number_of_values = 1)]
+    pub extra_params: Vec<KeyVal>,
+
+    /// What to print the request as
+    #[clap(short, long, value_enum, default_value_t = ExportFormat::Curl)]
+    pub format: ExportFormat,
+
+    /// Print the tokens, passwords, signatures and cookies instead of `<redacted>`
+    #[clap(long)]
+    pub show_secrets: bool,
+
+    /// Configuration to use.
+    #[clap(short, long, value_parser)]
+    pub config: Option<String>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A curl command
+    Curl,
+    /// An HTTPie command
+    Httpie,
+    /// A Rust snippet using reqwest
+    Reqwest,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ImportArgs {
+    #[clap(subcommand)]
+    pub source: ImportSource,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DiffImportArgs {
+    #[clap(subcommand)]
+    pub source: ImportSource,
+
+    /// Base url of the second server, used as the other side of each profile
+    #[clap(long, value_parser, global = true)]
+    pub base2: Option<Url>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+#[non_exhaustive]
+pub enum ImportSource {
+    /// A curl command line, e.g. one copied from the browser devtools
+    Curl(CurlArgs),
+    /// A HAR file captured by a browser or a proxy, one profile per entry
+    Har(HarArgs),
+    /// An OpenAPI 3 spec in YAML or JSON, one profile per operation
+    Openapi(OpenapiArgs),
+    /// A Postman v2.1 collection, one profile per request
+    Postman(PostmanArgs),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CurlArgs {
+    /// The curl command, starting with `curl`
+    #[clap(value_parser)]
+    pub command: String,
+
+    /// Name of the imported profile
+    #[clap(short, long, value_parser, default_value = "imported")]
+    pub profile: String,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct HarArgs {
+    /// The HAR file
+    #[clap(value_parser)]
+    pub file: PathBuf,
+
+    /// Import only the urls matching this pattern, `*` matches anything
+    #[clap(long, value_parser)]
+    pub url: Option<String>,
+
+    /// Import only these methods, e.g. `--method get --method post`
+    #[clap(long, value_parser)]
+    pub method: Vec<String>,
+
+    /// Leave these headers out, e.g. `--strip-header cookie --strip-header user-agent`
+    #[clap(long, value_parser)]
+    pub strip_header: Vec<String>,
+
+    /// Write the recorded responses to this directory, one `(profile).txt` per profile,
+    /// for `xreq run --snapshot-dir`
+    #[clap(long, value_parser)]
+    pub snapshot_dir: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct OpenapiArgs {
+    /// The spec file
+    #[clap(value_parser)]
+    pub file: PathBuf,
+
+    /// Server to send the requests to, an index into the `servers` of the spec or a url
+    #[clap(long, value_parser)]
+    pub server: Option<String>,
+
+    /// Server for the other side of each xdiff profile, e.g. `--server2 1`
+    #[clap(long, value_parser)]
+    pub server2: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct PostmanArgs {
+    /// The collection file
+    #[clap(value_parser)]
+    pub file: PathBuf,
+
+    /// A Postman environment file, its values win over the collection variables
+    #[clap(short, long, value_parser)]
+    pub environment: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookiesAction {
+    /// Print the cookies in the jar
+    Show,
+    /// Remove the jar file
+    Clear,
+}
+
 #[derive(Debug, Clone)]
 pub enum KeyValType {
     Query,
     Header,
     Body,
+    /// fills a `{key}` placeholder in the url path
+    Path,
+    /// the whole body is read from stdin
+    Stdin,
 }
 
 #[derive(Debug, Clone)]
@@ -57,6 +274,13 @@ pub struct KeyVal {
 }
 
 pub fn parse_key_val(s: &str) -> Result<KeyVal> {
+    if s.trim() == "@-" {
+        return Ok(KeyVal {
+            key_type: KeyValType::Stdin,
+            key: String::new(),
+            value: String::new(),
+        });
+    }
     let mut parts = s.splitn(2, '=');
 
     let key = parts
@@ -71,6 +295,7 @@ pub fn parse_key_val(s: &str) -> Result<KeyVal> {
     let (key_type, key) = match key.chars().next() {
         Some('%') => (KeyValType::Header, &key[1..]),
         Some('@') => (KeyValType::Body, &key[1..]),
+        Some(':') => (KeyValType::Path, &key[1..]),
         Some(v) if v.is_alphabetic() => (KeyValType::Query, key),
         _ => return Err(anyhow!("Invalid key value pair")),
     };
@@ -82,23 +307,35 @@ pub fn parse_key_val(s: &str) -> Result<KeyVal> {
     })
 }
 
-impl From<Vec<KeyVal>> for ExtraArgs {
-    fn from(args: Vec<KeyVal>) -> Self {
+impl TryFrom<Vec<KeyVal>> for ExtraArgs {
+    type Error = anyhow::Error;
+
+    fn try_from(args: Vec<KeyVal>) -> Result<Self> {
         let mut headers = vec![];
         let mut query = vec![];
         let mut body = vec![];
+        let mut path = vec![];
+        let mut body_raw = None;
 
         for arg in args {
             match arg.key_type {
                 KeyValType::Header => headers.push((arg.key, arg.value)),
                 KeyValType::Query => query.push((arg.key, arg.value)),
-                KeyValType::Body => body.push((arg.key, arg.value)),
+                KeyValType::Body => body.push((arg.key, parse_value(&arg.value))),
+                KeyValType::Path => path.push((arg.key, arg.value)),
+                KeyValType::Stdin => {
+                    let mut content = vec![];
+                    std::io::stdin().read_to_end(&mut content)?;
+                    body_raw = Some(content);
+                }
             }
         }
-        ExtraArgs {
+        Ok(ExtraArgs {
             headers,
             query,
             body,
-        }
+            path,
+            body_raw,
+        })
     }
 }
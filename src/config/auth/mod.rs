@@ -0,0 +1,255 @@
+mod digest;
+mod oauth2;
+
+use super::chain::{render_vars, Vars};
+use anyhow::{anyhow, Context, Result};
+use digest::DigestChallenge;
+use rand::Rng;
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Client, Method,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::PathBuf};
+
+pub use oauth2::OAuth2;
+
+/// Authentication scheme applied to a request profile before it is sent
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Auth {
+    /// `Authorization: Basic base64(username:password)`
+    Basic {
+        username: String,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        password: Option<Secret>,
+    },
+    /// `Authorization: Bearer <token>`
+    Bearer { token: Secret },
+    /// HTTP Digest, answered after the server sends its `401` challenge
+    Digest { username: String, password: Secret },
+    /// OAuth2 client credentials, the token is fetched from `token_url`
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2),
+}
+
+/// A credential which could be given inline, or read from an env var or a file
+///
+/// an inline value is written as `<redacted>` when the config is printed, unless it's only
+/// a `{{name}}` reference.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Secret {
+    Env {
+        env: String,
+    },
+    File {
+        file: PathBuf,
+    },
+    #[serde(serialize_with = "serialize_redacted")]
+    Value(String),
+}
+
+pub(crate) const REDACTED: &str = "<redacted>";
+
+impl Auth {
+    /// add the `Authorization` header for the schemes that don't need a challenge
+    ///
+    /// oauth2 tokens are fetched with `client`, which should have the network settings of
+    /// the profile.
+    pub async fn apply(&self, headers: &mut HeaderMap, client: &Client) -> Result<()> {
+        let value = match self {
+            Auth::Basic { username, password } => {
+                let password = match password {
+                    Some(password) => password.resolve()?,
+                    None => String::new(),
+                };
+                let credentials = base64::encode(format!("{}:{}", username, password));
+                format!("Basic {}", credentials)
+            }
+            Auth::Bearer { token } => format!("Bearer {}", token.resolve()?),
+            Auth::OAuth2(oauth2) => oauth2.authorization(client).await?,
+            Auth::Digest { .. } => return Ok(()),
+        };
+        headers.insert(header::AUTHORIZATION, sensitive_header(&value)?);
+        Ok(())
+    }
+
+    /// a copy with the variables filled into the usernames and inline secrets
+    pub fn render(&self, vars: &Vars) -> Auth {
+        let text = |s: &String| render_vars(s, vars);
+        match self {
+            Auth::Basic { username, password } => Auth::Basic {
+                username: text(username),
+                password: password.as_ref().map(|p| p.render(vars)),
+            },
+            Auth::Bearer { token } => Auth::Bearer {
+                token: token.render(vars),
+            },
+            Auth::Digest { username, password } => Auth::Digest {
+                username: text(username),
+                password: password.render(vars),
+            },
+            Auth::OAuth2(oauth2) => Auth::OAuth2(OAuth2 {
+                client_id: text(&oauth2.client_id),
+                client_secret: oauth2.client_secret.render(vars),
+                ..oauth2.clone()
+            }),
+        }
+    }
+
+    /// a secret given inline, which is left out when the config is printed
+    pub fn has_inline_secret(&self) -> bool {
+        match self {
+            Auth::Basic { password, .. } => password.as_ref().is_some_and(Secret::is_inline),
+            Auth::Bearer { token } => token.is_inline(),
+            Auth::Digest { password, .. } => password.is_inline(),
+            Auth::OAuth2(oauth2) => oauth2.client_secret.is_inline(),
+        }
+    }
+
+    /// answer a `WWW-Authenticate` challenge, only digest auth has something to say
+    pub fn respond(
+        &self,
+        challenge: &HeaderValue,
+        method: &Method,
+        uri: &str,
+    ) -> Result<Option<HeaderValue>> {
+        match self {
+            Auth::Digest { username, password } => {
+                let challenge = DigestChallenge::parse(challenge.to_str()?)?;
+                let cnonce = hex::encode(rand::thread_rng().gen::<[u8; 8]>());
+                let value = challenge.response(
+                    username,
+                    &password.resolve()?,
+                    method.as_str(),
+                    uri,
+                    &cnonce,
+                )?;
+                Ok(Some(sensitive_header(&value)?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Secret {
+    fn render(&self, vars: &Vars) -> Secret {
+        match self {
+            Secret::Value(value) => Secret::Value(render_vars(value, vars)),
+            secret => secret.clone(),
+        }
+    }
+
+    fn is_inline(&self) -> bool {
+        matches!(self, Secret::Value(value) if !is_reference(value))
+    }
+
+    pub fn resolve(&self) -> Result<String> {
+        match self {
+            Secret::Env { env } => {
+                std::env::var(env).with_context(|| format!("env var {} is not set", env))
+            }
+            Secret::File { file } => {
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("failed to read secret file {}", file.display()))?;
+                Ok(content.trim().to_string())
+            }
+            Secret::Value(value) if value == REDACTED => Err(anyhow!(
+                "the secret was redacted when the config was printed, set it again"
+            )),
+            Secret::Value(value) => Ok(value.clone()),
+        }
+    }
+}
+
+fn serialize_redacted<S: serde::Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    if is_reference(value) {
+        serializer.serialize_str(value)
+    } else {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+/// a value like `{{token}}`, filled from the vars when the request is sent
+fn is_reference(value: &str) -> bool {
+    value
+        .strip_prefix("{{")
+        .and_then(|v| v.strip_suffix("}}"))
+        .is_some_and(|name| !name.is_empty() && !name.contains(['{', '}']))
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Env { env } => f.debug_struct("Env").field("env", env).finish(),
+            Secret::File { file } => f.debug_struct("File").field("file", file).finish(),
+            Secret::Value(value) if is_reference(value) => write!(f, "{:?}", value),
+            Secret::Value(_) => write!(f, "{:?}", REDACTED),
+        }
+    }
+}
+
+fn sensitive_header(value: &str) -> Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(value)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn basic_auth_should_work() {
+        let auth = Auth::Basic {
+            username: "Aladdin".into(),
+            password: Some(Secret::Value("open sesame".into())),
+        };
+        let mut headers = HeaderMap::new();
+        auth.apply(&mut headers, &Client::new()).await.unwrap();
+        assert_eq!(
+            headers[header::AUTHORIZATION],
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        assert!(headers[header::AUTHORIZATION].is_sensitive());
+    }
+
+    #[tokio::test]
+    async fn bearer_token_from_env_should_work() {
+        std::env::set_var("XDIFF_TEST_BEARER_TOKEN", "abc");
+        let auth: Auth =
+            serde_yaml::from_str("type: bearer\ntoken:\n  env: XDIFF_TEST_BEARER_TOKEN").unwrap();
+        let mut headers = HeaderMap::new();
+        auth.apply(&mut headers, &Client::new()).await.unwrap();
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer abc");
+    }
+
+    #[test]
+    fn secret_debug_should_be_redacted() {
+        let auth = Auth::Bearer {
+            token: Secret::Value("abc".into()),
+        };
+        assert_eq!(format!("{:?}", auth), "Bearer { token: \"<redacted>\" }");
+    }
+
+    #[test]
+    fn secret_value_should_be_redacted_when_printed() {
+        let auth = Auth::Basic {
+            username: "user".into(),
+            password: Some(Secret::Value("pass".into())),
+        };
+        let yaml = serde_yaml::to_string(&auth).unwrap();
+        assert_eq!(yaml, "type: basic\nusername: user\npassword: <redacted>\n");
+        let auth: Auth = serde_yaml::from_str(&yaml).unwrap();
+        let mut headers = HeaderMap::new();
+        assert!(futures::executor::block_on(auth.apply(&mut headers, &Client::new())).is_err());
+
+        let token = Secret::Value("{{token}}".into());
+        assert_eq!(serde_yaml::to_string(&token).unwrap(), "'{{token}}'\n");
+        let token = Secret::Env {
+            env: "TOKEN".into(),
+        };
+        assert_eq!(serde_yaml::to_string(&token).unwrap(), "env: TOKEN\n");
+    }
+}
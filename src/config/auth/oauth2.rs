@@ -0,0 +1,259 @@
+use super::Secret;
+use anyhow::{anyhow, Context, Result};
+use reqwest::{header, Client, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::fs;
+
+/// tokens expiring within this many seconds are fetched again
+const EXPIRY_MARGIN: u64 = 30;
+/// how long a token is kept when the server doesn't say when it expires
+const DEFAULT_TTL: u64 = 300;
+
+/// OAuth2 client-credentials grant, tokens are cached on disk until they expire
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct OAuth2 {
+    pub token_url: Url,
+    pub client_id: String,
+    pub client_secret: Secret,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub scopes: Vec<String>,
+    /// where to keep the fetched tokens, defaults to `<user cache dir>/xdiff/oauth2`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cache_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedToken {
+    access_token: String,
+    token_type: String,
+    /// unix timestamp in seconds
+    expires_at: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+impl OAuth2 {
+    /// get the `Authorization` header value, from cache if possible
+    pub async fn authorization(&self, client: &Client) -> Result<String> {
+        let path = self.cache_path()?;
+        let token = match load_cached(&path).await {
+            Some(token) => token,
+            None => {
+                let token = self.fetch(client).await?;
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).await?;
+                }
+                write_private(&path, &serde_json::to_vec(&token)?).await?;
+                token
+            }
+        };
+        Ok(format!("{} {}", token.token_type, token.access_token))
+    }
+
+    async fn fetch(&self, client: &Client) -> Result<CachedToken> {
+        let secret = self.client_secret.resolve()?;
+        let scope = self.scopes.join(" ");
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", &self.client_id),
+            ("client_secret", &secret),
+        ];
+        if !scope.is_empty() {
+            form.push(("scope", &scope));
+        }
+
+        let res = client
+            .post(self.token_url.clone())
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(header::ACCEPT, "application/json")
+            .body(serde_urlencoded::to_string(&form)?)
+            .send()
+            .await
+            .with_context(|| format!("failed to request token from {}", self.token_url))?;
+        let status = res.status();
+        if !status.is_success() {
+            return Err(anyhow!(
+                "token endpoint {} returned {}: {}",
+                self.token_url,
+                status,
+                res.text().await.unwrap_or_default()
+            ));
+        }
+        let token: TokenResponse =
+            serde_json::from_slice(&res.bytes().await?).context("invalid token response")?;
+
+        Ok(CachedToken {
+            access_token: token.access_token,
+            // normalize `bearer` as some servers send it in lower case
+            token_type: match token.token_type {
+                Some(t) if !t.eq_ignore_ascii_case("bearer") => t,
+                _ => "Bearer".to_string(),
+            },
+            expires_at: now() + token.expires_in.unwrap_or(DEFAULT_TTL),
+        })
+    }
+
+    fn cache_path(&self) -> Result<PathBuf> {
+        let dir = match self.cache_dir.as_ref() {
+            Some(dir) => dir.clone(),
+            None => dirs::cache_dir()
+                .ok_or_else(|| anyhow!("no cache dir found, please set cache_dir"))?
+                .join("xdiff")
+                .join("oauth2"),
+        };
+        // a new secret gets a new token
+        let secret = hex::encode(Sha256::digest(self.client_secret.resolve()?));
+        let key = format!(
+            "{}\n{}\n{}\n{}",
+            self.token_url,
+            self.client_id,
+            secret,
+            self.scopes.join(" ")
+        );
+        Ok(dir.join(format!("{}.json", hex::encode(Sha256::digest(key)))))
+    }
+}
+
+/// tokens are credentials, so only the user could read them
+#[cfg(unix)]
+async fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .await
+        .with_context(|| format!("failed to write token cache {}", path.display()))?;
+    // the mode is only set on new files
+    file.set_permissions(Permissions::from_mode(0o600)).await?;
+    file.write_all(content).await?;
+    Ok(())
+}
+
+/// the cache dir is in the user's profile, which other users can't read
+#[cfg(not(unix))]
+async fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    fs::write(path, content)
+        .await
+        .with_context(|| format!("failed to write token cache {}", path.display()))
+}
+
+async fn load_cached(path: &Path) -> Option<CachedToken> {
+    let content = fs::read(path).await.ok()?;
+    let token: CachedToken = serde_json::from_slice(&content).ok()?;
+    if token.expires_at <= now() + EXPIRY_MARGIN {
+        return None;
+    }
+    Some(token)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    fn oauth2(cache_dir: PathBuf) -> OAuth2 {
+        OAuth2 {
+            token_url: Url::parse(&format!("{}/oauth2/token", mockito::server_url())).unwrap(),
+            client_id: "xdiff".into(),
+            client_secret: Secret::Value("secret".into()),
+            scopes: vec!["read".into(), "write".into()],
+            cache_dir: Some(cache_dir),
+        }
+    }
+
+    #[tokio::test]
+    async fn oauth2_token_should_be_fetched_once_and_cached() {
+        let m = mock("POST", "/oauth2/token")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("grant_type".into(), "client_credentials".into()),
+                Matcher::UrlEncoded("client_secret".into(), "secret".into()),
+                Matcher::UrlEncoded("scope".into(), "read write".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token":"t0k","token_type":"bearer","expires_in":3600}"#)
+            .expect(1)
+            .create();
+
+        let dir = tempfile::tempdir().unwrap();
+        let auth = oauth2(dir.path().to_path_buf());
+        let client = Client::new();
+        assert_eq!(auth.authorization(&client).await.unwrap(), "Bearer t0k");
+        assert_eq!(auth.authorization(&client).await.unwrap(), "Bearer t0k");
+        m.assert();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(auth.cache_path().unwrap())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn oauth2_token_should_expire_without_expires_in() {
+        let m = mock("POST", "/oauth2/token-no-expiry")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token":"t0k"}"#)
+            .create();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut auth = oauth2(dir.path().to_path_buf());
+        auth.token_url.set_path("/oauth2/token-no-expiry");
+        let token = auth.fetch(&Client::new()).await.unwrap();
+        assert!(token.expires_at <= now() + DEFAULT_TTL);
+        m.assert();
+    }
+
+    #[test]
+    fn oauth2_cache_path_should_change_with_secret() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut auth = oauth2(dir.path().to_path_buf());
+        let path = auth.cache_path().unwrap();
+        auth.client_secret = Secret::Value("rotated".into());
+        assert_ne!(auth.cache_path().unwrap(), path);
+    }
+
+    #[tokio::test]
+    async fn expired_token_should_not_be_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token.json");
+        let token = CachedToken {
+            access_token: "old".into(),
+            token_type: "Bearer".into(),
+            expires_at: now() + 5,
+        };
+        fs::write(&path, serde_json::to_vec(&token).unwrap())
+            .await
+            .unwrap();
+        assert!(load_cached(&path).await.is_none());
+    }
+}
@@ -0,0 +1,282 @@
+use super::{decode_braces, RequestProfile, ResponseExt};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// A request run before the profile's own request, its response is captured into variables
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetupStep {
+    #[serde(flatten)]
+    pub request: RequestProfile,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub capture: BTreeMap<String, Capture>,
+}
+
+/// Where to take a variable's value from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Capture {
+    /// a json path into the body, e.g. `$.data.token` or `$.items[0].id`
+    Json { json: String },
+    /// a response header
+    Header { header: String },
+}
+
+impl Capture {
+    pub async fn extract(res: ResponseExt, captures: &BTreeMap<String, Capture>) -> Result<Vars> {
+        let res = res.into_inner();
+        let status = res.status();
+        let headers = res.headers().clone();
+        let text = res.text().await?;
+        let mut json = None;
+
+        let mut vars = Vars::new();
+        for (name, capture) in captures {
+            let value = match capture {
+                Capture::Header { header: key } => headers
+                    .get(key.as_str())
+                    .map(|v| v.to_str())
+                    .transpose()?
+                    .map(|v| v.to_string()),
+                Capture::Json { json: path } => {
+                    if json.is_none() {
+                        json = Some(
+                            serde_json::from_str::<serde_json::Value>(&text).with_context(
+                                || format!("response ({}) is not json: {}", status, text),
+                            )?,
+                        );
+                    }
+                    lookup_json(json.as_ref().unwrap(), path)?.map(|v| match v {
+                        serde_json::Value::String(s) => s.clone(),
+                        v => v.to_string(),
+                    })
+                }
+            };
+            let value = value.ok_or_else(|| {
+                anyhow!("failed to capture {} with {:?} ({})", name, capture, status)
+            })?;
+            vars.insert(name.clone(), value);
+        }
+        Ok(vars)
+    }
+}
+
+pub type Vars = HashMap<String, String>;
+
+/// follow a path like `$.data.items[0].id` (the leading `$.` is optional)
+pub fn lookup_json<'a>(
+    value: &'a serde_json::Value,
+    path: &str,
+) -> Result<Option<&'a serde_json::Value>> {
+    let path = path.trim_start_matches('$').trim_start_matches('.');
+    let mut current = value;
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let (key, indexes) = match segment.find('[') {
+            Some(idx) => segment.split_at(idx),
+            None => (segment, ""),
+        };
+        if !key.is_empty() {
+            current = match current.get(key) {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+        }
+        for index in indexes.split(']').filter(|s| !s.is_empty()) {
+            let index: usize = index
+                .trim_start_matches('[')
+                .parse()
+                .with_context(|| format!("invalid index in json path: {}", path))?;
+            current = match current.get(index) {
+                Some(v) => v,
+                None => return Ok(None),
+            };
+        }
+    }
+    Ok(Some(current))
+}
+
+/// replace every `{{name}}` in `s`, unknown variables are left as they are
+pub fn render_vars(s: &str, vars: &Vars) -> String {
+    let mut output = s.to_string();
+    for (k, v) in vars {
+        output = output.replace(&format!("{{{{{}}}}}", k), v);
+    }
+    output
+}
+
+impl RequestProfile {
+    /// a copy of the profile with the variables filled into the url, params, headers, body
+    /// and auth
+    ///
+    /// the setup steps are dropped from the copy, as their work is already done, and a
+    /// `unix://` url is split into its socket and http url.
+    pub fn render(&self, vars: &Vars) -> Result<RequestProfile> {
+        let mut profile = self.clone();
+        profile.setup.clear();
+        if !vars.is_empty() {
+            let url = decode_braces(self.url.as_str());
+            profile.url = render_vars(&url, vars).parse()?;
+            profile.params = self.params.as_ref().map(|v| render_json(v, vars));
+            profile.body = self.body.as_ref().map(|v| render_json(v, vars));
+            profile.body_raw = self.body_raw.as_ref().map(|v| render_vars(v, vars));
+            for v in profile.path_params.values_mut() {
+                *v = render_vars(v, vars);
+            }
+            profile.auth = self.auth.as_ref().map(|a| a.render(vars));
+
+            let mut headers = HeaderMap::new();
+            for (k, v) in self.headers.iter() {
+                let value = match v.to_str() {
+                    Ok(s) => HeaderValue::from_str(&render_vars(s, vars))?,
+                    Err(_) => v.clone(),
+                };
+                headers.append(k, value);
+            }
+            profile.headers = headers;
+        }
+        if profile.url.scheme() == "unix" {
+            let (socket, url) = split_unix_url(&profile.url)?;
+            profile.socket = Some(socket);
+            profile.url = url;
+        }
+        Ok(profile)
+    }
+}
+
+/// split `unix:///path/to.sock:/http/path?query` into the socket and the http url
+fn split_unix_url(url: &Url) -> Result<(PathBuf, Url)> {
+    let path = url.path();
+    let (socket, path) = match path.find(":/") {
+        Some(idx) => (&path[..idx], &path[idx + 1..]),
+        None => (path.trim_end_matches(':'), "/"),
+    };
+    if socket.is_empty() {
+        return Err(anyhow!("no socket path in {}", url));
+    }
+    let mut http_url: Url = format!("http://localhost{}", path).parse()?;
+    http_url.set_query(url.query());
+    Ok((PathBuf::from(socket), http_url))
+}
+
+fn render_json(value: &serde_json::Value, vars: &Vars) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => serde_json::Value::String(render_vars(s, vars)),
+        serde_json::Value::Array(arr) => {
+            serde_json::Value::Array(arr.iter().map(|v| render_json(v, vars)).collect())
+        }
+        serde_json::Value::Object(obj) => serde_json::Value::Object(
+            obj.iter()
+                .map(|(k, v)| (k.clone(), render_json(v, vars)))
+                .collect(),
+        ),
+        v => v.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Auth, Secret};
+    use serde_json::json;
+
+    #[test]
+    fn lookup_json_should_work() {
+        let value = json!({"data": {"token": "abc", "items": [{"id": 1}, {"id": 2}]}});
+        assert_eq!(
+            lookup_json(&value, "$.data.token").unwrap(),
+            Some(&json!("abc"))
+        );
+        assert_eq!(
+            lookup_json(&value, "data.items[1].id").unwrap(),
+            Some(&json!(2))
+        );
+        assert_eq!(lookup_json(&value, "$.data.nope").unwrap(), None);
+    }
+
+    #[test]
+    fn request_profile_render_should_work() {
+        let profile: RequestProfile = serde_yaml::from_str(
+            r#"
+url: http://localhost/users/{{user_id}}/todos
+headers:
+  authorization: "Bearer {{token}}"
+body:
+  owner: "{{user_id}}"
+auth:
+  type: bearer
+  token: "{{token}}"
+"#,
+        )
+        .unwrap();
+        let vars: Vars = [("user_id", "42"), ("token", "abc")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let profile = profile.render(&vars).unwrap();
+        assert_eq!(profile.url.as_str(), "http://localhost/users/42/todos");
+        assert_eq!(profile.headers["authorization"], "Bearer abc");
+        assert_eq!(profile.body, Some(json!({"owner": "42"})));
+        assert_eq!(
+            profile.auth,
+            Some(Auth::Bearer {
+                token: Secret::Value("abc".into())
+            })
+        );
+    }
+
+    #[test]
+    fn request_profile_render_should_fill_raw_body() {
+        let profile: RequestProfile = serde_yaml::from_str(
+            r#"
+url: http://localhost/todos
+headers:
+  content-type: application/xml
+body_raw: "<todo owner=\"{{user_id}}\"/>"
+"#,
+        )
+        .unwrap();
+        let vars: Vars = [("user_id".to_string(), "42".to_string())]
+            .into_iter()
+            .collect();
+        let profile = profile.render(&vars).unwrap();
+        assert_eq!(profile.body_raw.as_deref(), Some("<todo owner=\"42\"/>"));
+    }
+
+    #[test]
+    fn request_profile_render_should_fill_path_params() {
+        let profile: RequestProfile = serde_yaml::from_str(
+            r#"
+url: http://localhost/users/{user_id}/todos
+path_params:
+  user_id: "{{user_id}}"
+"#,
+        )
+        .unwrap();
+        let vars: Vars = [("user_id".to_string(), "42".to_string())]
+            .into_iter()
+            .collect();
+        let profile = profile.render(&vars).unwrap();
+        assert_eq!(
+            profile.get_url(&Default::default()).unwrap(),
+            "http://localhost/users/42/todos"
+        );
+    }
+
+    #[test]
+    fn split_unix_url_should_work() {
+        let url = "unix:///var/run/app.sock:/api/todos?id=1".parse().unwrap();
+        let (socket, url) = split_unix_url(&url).unwrap();
+        assert_eq!(socket, PathBuf::from("/var/run/app.sock"));
+        assert_eq!(url.as_str(), "http://localhost/api/todos?id=1");
+
+        let url = "unix:///var/run/app.sock".parse().unwrap();
+        let (socket, url) = split_unix_url(&url).unwrap();
+        assert_eq!(socket, PathBuf::from("/var/run/app.sock"));
+        assert_eq!(url.as_str(), "http://localhost/");
+    }
+}
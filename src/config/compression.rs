@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Response, ResponseBuilderExt,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, io::Read};
+
+/// Which encodings to ask for, and whether to decode the body before it's compared
+///
+/// without a `compression` setting nothing is asked for, but encoded responses are still
+/// decoded.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Compression {
+    /// sent as `accept-encoding`, unless the profile sets that header itself, all the
+    /// supported encodings if not given
+    #[serde(default = "default_accept")]
+    pub accept: Vec<Encoding>,
+    #[serde(default = "default_decode")]
+    pub decode: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+    Br,
+    Zstd,
+}
+
+/// How a response body was encoded, with its size on the wire and once decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingReport {
+    pub encoding: String,
+    pub compressed: usize,
+    pub decoded: usize,
+}
+
+impl Compression {
+    pub fn apply(&self, headers: &mut HeaderMap) -> Result<()> {
+        if self.accept.is_empty() || headers.contains_key(header::ACCEPT_ENCODING) {
+            return Ok(());
+        }
+        let accept: Vec<_> = self.accept.iter().map(|e| e.as_str()).collect();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_str(&accept.join(", "))?,
+        );
+        Ok(())
+    }
+
+    /// decode the body of an encoded response
+    ///
+    /// the decoded response drops `content-encoding` and `content-length`, as reqwest
+    /// does for the encodings it decodes itself. Responses in an unknown encoding are
+    /// returned as they are.
+    pub async fn decode(&self, res: Response) -> Result<(Response, Option<EncodingReport>)> {
+        let content_encoding = match res.headers().get(header::CONTENT_ENCODING) {
+            Some(v) if self.decode => v.to_str()?.to_string(),
+            _ => return Ok((res, None)),
+        };
+        let encodings: Option<Vec<_>> = content_encoding
+            .split(',')
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && !v.eq_ignore_ascii_case("identity"))
+            .map(Encoding::parse)
+            .collect();
+        let encodings = match encodings {
+            Some(encodings) if !encodings.is_empty() => encodings,
+            _ => return Ok((res, None)),
+        };
+
+        let status = res.status();
+        let version = res.version();
+        let url = res.url().clone();
+        let mut headers = res.headers().clone();
+        let compressed = res.bytes().await?;
+        // encodings are listed in the order they were applied
+        let mut body = compressed.to_vec();
+        for encoding in encodings.iter().rev() {
+            body = encoding
+                .decode(&body)
+                .with_context(|| format!("failed to decode {} body", encoding.as_str()))?;
+        }
+        headers.remove(header::CONTENT_ENCODING);
+        headers.remove(header::CONTENT_LENGTH);
+
+        let report = EncodingReport {
+            encoding: content_encoding,
+            compressed: compressed.len(),
+            decoded: body.len(),
+        };
+        let mut builder = http::Response::builder()
+            .status(status)
+            .version(version)
+            .url(url);
+        if let Some(h) = builder.headers_mut() {
+            *h = headers;
+        }
+        Ok((builder.body(body)?.into(), Some(report)))
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            accept: vec![],
+            decode: default_decode(),
+        }
+    }
+}
+
+impl Encoding {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            "br" => Some(Encoding::Br),
+            "zstd" => Some(Encoding::Zstd),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Br => "br",
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut output = vec![];
+        match self {
+            Encoding::Gzip => {
+                flate2::read::MultiGzDecoder::new(data).read_to_end(&mut output)?;
+            }
+            Encoding::Deflate => {
+                // `deflate` should be zlib wrapped, but some servers send the raw stream
+                if flate2::read::ZlibDecoder::new(data)
+                    .read_to_end(&mut output)
+                    .is_err()
+                {
+                    output.clear();
+                    flate2::read::DeflateDecoder::new(data).read_to_end(&mut output)?;
+                }
+            }
+            Encoding::Br => {
+                brotli::Decompressor::new(data, 4096).read_to_end(&mut output)?;
+            }
+            Encoding::Zstd => {
+                zstd::stream::read::Decoder::new(data)?.read_to_end(&mut output)?;
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl fmt::Display for EncodingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} -> {} bytes)",
+            self.encoding, self.compressed, self.decoded
+        )
+    }
+}
+
+fn default_accept() -> Vec<Encoding> {
+    vec![
+        Encoding::Gzip,
+        Encoding::Deflate,
+        Encoding::Br,
+        Encoding::Zstd,
+    ]
+}
+
+fn default_decode() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const DATA: &[u8] = b"{\"hello\": \"world\", \"hello again\": \"world\"}";
+
+    #[test]
+    fn encoding_decode_should_work() {
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(DATA).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(Encoding::Gzip.decode(&gzip).unwrap(), DATA);
+
+        let mut deflate =
+            flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+        deflate.write_all(DATA).unwrap();
+        let deflate = deflate.finish().unwrap();
+        assert_eq!(Encoding::Deflate.decode(&deflate).unwrap(), DATA);
+
+        let mut br = vec![];
+        brotli::CompressorWriter::new(&mut br, 4096, 5, 22)
+            .write_all(DATA)
+            .unwrap();
+        assert_eq!(Encoding::Br.decode(&br).unwrap(), DATA);
+
+        let zstd = zstd::encode_all(DATA, 0).unwrap();
+        assert_eq!(Encoding::Zstd.decode(&zstd).unwrap(), DATA);
+    }
+
+    #[test]
+    fn compression_apply_should_keep_existing_header() {
+        let mut headers = HeaderMap::new();
+        Compression::default().apply(&mut headers).unwrap();
+        assert!(headers.is_empty());
+
+        let compression: Compression = serde_yaml::from_str("decode: true").unwrap();
+        compression.apply(&mut headers).unwrap();
+        assert_eq!(headers["accept-encoding"], "gzip, deflate, br, zstd");
+
+        headers.insert("accept-encoding", HeaderValue::from_static("identity"));
+        compression.apply(&mut headers).unwrap();
+        assert_eq!(headers["accept-encoding"], "identity");
+    }
+}
@@ -0,0 +1,291 @@
+use super::import::resolve;
+use anyhow::{anyhow, Context, Result};
+use jsonschema::JSONSchema;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The contract each response of a profile is checked against
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Contract {
+    /// a JSON Schema file, in YAML or JSON
+    Schema { schema: PathBuf },
+    /// the response schemas of an operation of an OpenAPI 3 spec, picked by status
+    Openapi { openapi: PathBuf, operation: String },
+}
+
+/// A contract with its schemas compiled
+#[derive(Debug)]
+pub(crate) enum LoadedContract {
+    Schema(JSONSchema),
+    /// by the status keys of the responses, like `200`, `4XX` or `default`, `None` for
+    /// responses without a json body
+    Openapi(BTreeMap<String, Option<JSONSchema>>),
+}
+
+/// The contract a profile loaded last, with what it was loaded from
+pub(crate) type ContractCache = Arc<Mutex<Option<(Contract, Arc<LoadedContract>)>>>;
+
+impl Contract {
+    pub(crate) fn load(&self) -> Result<LoadedContract> {
+        match self {
+            Contract::Schema { schema } => LoadedContract::from_schema(&read_yaml(schema)?)
+                .with_context(|| format!("invalid schema {}", schema.display())),
+            Contract::Openapi { openapi, operation } => {
+                LoadedContract::from_openapi(&read_yaml(openapi)?, operation)
+                    .with_context(|| format!("invalid contract {}", openapi.display()))
+            }
+        }
+    }
+}
+
+impl LoadedContract {
+    fn from_schema(schema: &Value) -> Result<Self> {
+        Ok(LoadedContract::Schema(compile(schema)?))
+    }
+
+    fn from_openapi(spec: &Value, operation: &str) -> Result<Self> {
+        let op = find_operation(spec, operation)
+            .with_context(|| format!("operation {} not found", operation))?;
+        let mut schemas = BTreeMap::new();
+        let responses = op.get("responses").and_then(|v| v.as_object());
+        for (status, response) in responses.into_iter().flatten() {
+            let schema = match response_schema(spec, resolve(spec, response)?)? {
+                Some(schema) => Some(
+                    compile(&schema)
+                        .with_context(|| format!("invalid schema for status {}", status))?,
+                ),
+                None => None,
+            };
+            schemas.insert(status.to_uppercase(), schema);
+        }
+        Ok(LoadedContract::Openapi(schemas))
+    }
+
+    /// the violations of a response, empty if it keeps to the contract
+    pub(crate) fn check(&self, status: StatusCode, body: &str) -> Vec<String> {
+        let validator = match self {
+            LoadedContract::Schema(validator) => validator,
+            LoadedContract::Openapi(schemas) => {
+                // the exact status first, then its range, then the default
+                let code = status.as_u16().to_string();
+                let range = format!("{}XX", &code[..1]);
+                let schema = [code.as_str(), range.as_str(), "DEFAULT"]
+                    .iter()
+                    .find_map(|key| schemas.get(*key));
+                match schema {
+                    Some(Some(validator)) => validator,
+                    Some(None) => return vec![],
+                    None => return vec![format!("status {} is not declared", code)],
+                }
+            }
+        };
+        let body: Value = match serde_json::from_str(body) {
+            Ok(body) => body,
+            Err(_) => return vec!["body is not json".to_string()],
+        };
+        let violations = match validator.validate(&body) {
+            Ok(()) => vec![],
+            Err(errors) => errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    let path = if path.is_empty() { "/".into() } else { path };
+                    format!("{}: {}", path, e)
+                })
+                .collect(),
+        };
+        violations
+    }
+}
+
+fn compile(schema: &Value) -> Result<JSONSchema> {
+    JSONSchema::compile(schema).map_err(|e| anyhow!("invalid schema: {}", e))
+}
+
+fn read_yaml(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read contract {}", path.display()))?;
+    // json is yaml as well
+    serde_yaml::from_str(&content).with_context(|| format!("invalid contract {}", path.display()))
+}
+
+fn find_operation<'a>(spec: &'a Value, operation: &str) -> Result<&'a Value> {
+    let paths = spec.get("paths").and_then(|v| v.as_object());
+    for item in paths.into_iter().flatten().map(|(_, item)| item) {
+        let item = resolve(spec, item)?;
+        let found = item
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(_, op)| op)
+            .find(|op| op.get("operationId").and_then(|v| v.as_str()) == Some(operation));
+        if let Some(op) = found {
+            return Ok(op);
+        }
+    }
+    Err(anyhow!("no operation with this operationId"))
+}
+
+/// the json schema of a response, with the components of the spec to resolve its refs
+fn response_schema(spec: &Value, response: &Value) -> Result<Option<Value>> {
+    let content = match response.get("content").and_then(|v| v.as_object()) {
+        Some(content) => content,
+        None => return Ok(None),
+    };
+    let media = content
+        .get("application/json")
+        .or_else(|| {
+            content
+                .iter()
+                .find(|(t, _)| t.ends_with("+json"))
+                .map(|(_, v)| v)
+        })
+        .map(|media| resolve(spec, media))
+        .transpose()?;
+    let mut schema = match media.and_then(|m| m.get("schema")) {
+        Some(schema) => schema.clone(),
+        None => return Ok(None),
+    };
+    if let (Value::Object(obj), Some(components)) = (&mut schema, spec.get("components")) {
+        obj.insert("components".into(), components.clone());
+    }
+    to_json_schema(&mut schema);
+    Ok(Some(schema))
+}
+
+/// OpenAPI 3.0 schemas are JSON Schema draft 4 with a few changes, the validator uses draft 7
+///
+/// a nullable type is `nullable: true`, and `exclusiveMinimum: true` makes the `minimum`
+/// exclusive where draft 7 gives the bound itself as `exclusiveMinimum`.
+fn to_json_schema(schema: &mut Value) {
+    match schema {
+        Value::Object(obj) => {
+            if obj.remove("nullable") == Some(Value::Bool(true)) {
+                if let Some(Value::String(t)) = obj.get("type") {
+                    let types = vec![Value::String(t.clone()), Value::String("null".into())];
+                    obj.insert("type".into(), Value::Array(types));
+                }
+            }
+            for (exclusive, bound) in [
+                ("exclusiveMinimum", "minimum"),
+                ("exclusiveMaximum", "maximum"),
+            ] {
+                if let Some(Value::Bool(flag)) = obj.get(exclusive) {
+                    let flag = *flag;
+                    obj.remove(exclusive);
+                    if flag {
+                        if let Some(value) = obj.remove(bound) {
+                            obj.insert(exclusive.into(), value);
+                        }
+                    }
+                }
+            }
+            obj.values_mut().for_each(to_json_schema);
+        }
+        Value::Array(items) => items.iter_mut().for_each(to_json_schema),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+paths:
+  /items/{id}:
+    get:
+      operationId: getItem
+      responses:
+        "200":
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Item"
+        4XX:
+          $ref: "#/components/responses/Error"
+components:
+  schemas:
+    Item:
+      type: object
+      required: [id, price]
+      properties:
+        id:
+          type: integer
+        price:
+          type: number
+          minimum: 0
+          exclusiveMinimum: true
+          maximum: 1000
+          exclusiveMaximum: false
+        note:
+          type: string
+          nullable: true
+  responses:
+    Error:
+      content:
+        application/json:
+          schema:
+            type: object
+            required: [message]
+"##;
+
+    fn openapi_contract() -> LoadedContract {
+        let spec: Value = serde_yaml::from_str(SPEC).unwrap();
+        LoadedContract::from_openapi(&spec, "getItem").unwrap()
+    }
+
+    #[test]
+    fn openapi_contract_should_check_by_status() {
+        let contract = openapi_contract();
+        let ok = r#"{"id": 1, "price": 9.5, "note": null}"#;
+        assert!(contract.check(StatusCode::OK, ok).is_empty());
+
+        let broken = r#"{"id": 1, "price": "9.5"}"#;
+        let violations = contract.check(StatusCode::OK, broken);
+        assert_eq!(violations, vec![r#"/price: "9.5" is not of type "number""#]);
+
+        let violations = contract.check(StatusCode::NOT_FOUND, "{}");
+        assert_eq!(violations, vec![r#"/: "message" is a required property"#]);
+
+        let violations = contract.check(StatusCode::BAD_GATEWAY, "{}");
+        assert_eq!(violations, vec!["status 502 is not declared"]);
+    }
+
+    #[test]
+    fn openapi_contract_should_convert_exclusive_bounds() {
+        let contract = openapi_contract();
+        let violations = contract.check(StatusCode::OK, r#"{"id": 1, "price": 0}"#);
+        assert_eq!(
+            violations,
+            vec!["/price: 0 is less than or equal to the minimum of 0"]
+        );
+        let violations = contract.check(StatusCode::OK, r#"{"id": 1, "price": 1000}"#);
+        assert!(violations.is_empty());
+
+        let spec: Value = serde_yaml::from_str(SPEC).unwrap();
+        assert!(LoadedContract::from_openapi(&spec, "getItems").is_err());
+    }
+
+    #[test]
+    fn schema_contract_should_work() {
+        let schema = json!({"type": "array", "items": {"type": "string"}});
+        let contract = LoadedContract::from_schema(&schema).unwrap();
+        assert!(contract.check(StatusCode::OK, r#"["a", "b"]"#).is_empty());
+        assert_eq!(
+            contract.check(StatusCode::OK, r#"["a", 1]"#),
+            vec![r#"/1: 1 is not of type "string""#]
+        );
+        assert_eq!(
+            contract.check(StatusCode::OK, "<html>"),
+            vec!["body is not json"]
+        );
+        assert!(LoadedContract::from_schema(&json!({"type": 1})).is_err());
+    }
+}
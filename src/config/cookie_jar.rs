@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Context, Result};
+use cookie_store::{Cookie, CookieExpiration, CookieStore};
+use reqwest::{header::HeaderValue, Url};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    io::{BufReader, Write as _},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+};
+use tokio::fs;
+
+/// saves of all the jars go one at a time, so concurrent requests don't lose cookies
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// domain, path and name, which tell the cookies apart
+type CookieKey = (String, String, String);
+
+/// Cookie store of a profile, persisted as json lines so the session survives between runs
+#[derive(Debug)]
+pub struct CookieJar {
+    path: PathBuf,
+    store: RwLock<CookieStore>,
+    /// the cookies in the file when it was loaded, to tell which ones were dropped since
+    loaded: BTreeSet<CookieKey>,
+}
+
+impl CookieJar {
+    /// load the jar from `path`, a missing file is just an empty jar
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = match fs::read(&path).await {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).context(format!("failed to read {}", path.display())),
+        };
+        let store = parse_store(&path, content.as_deref())?;
+        Ok(Self {
+            loaded: store.iter_unexpired().map(cookie_key).collect(),
+            path,
+            store: RwLock::new(store),
+        })
+    }
+
+    /// write all the unexpired cookies back, session cookies included
+    ///
+    /// the file is merged rather than overwritten, so the cookies another run saved since
+    /// this one was loaded are kept, unless this jar dropped them.
+    pub async fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+        let content = match std::fs::read(&self.path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).context(format!("failed to read {}", self.path.display())),
+        };
+        let on_disk = parse_store(&self.path, content.as_deref())?;
+        let store = self.store.read().unwrap();
+        let mut cookies = BTreeMap::new();
+        for cookie in on_disk.iter_unexpired() {
+            let key = cookie_key(cookie);
+            if !self.loaded.contains(&key) {
+                cookies.insert(key, cookie);
+            }
+        }
+        for cookie in store.iter_unexpired() {
+            cookies.insert(cookie_key(cookie), cookie);
+        }
+
+        let mut content = vec![];
+        for cookie in cookies.values() {
+            writeln!(&mut content, "{}", serde_json::to_string(cookie)?)?;
+        }
+        // a reader never sees a half written jar
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// drop every cookie, both in memory and on disk
+    pub async fn clear(&self) -> Result<()> {
+        self.store.write().unwrap().clear();
+        match fs::remove_file(&self.path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// one line per cookie: `domain path name=value expires`
+    pub fn get_text(&self) -> Result<String> {
+        let mut output = String::new();
+        let store = self.store.read().unwrap();
+        for cookie in store.iter_unexpired() {
+            let expires = match &cookie.expires {
+                CookieExpiration::AtUtc(t) => t.to_string(),
+                CookieExpiration::SessionEnd => "session".to_string(),
+            };
+            writeln!(
+                &mut output,
+                "{} {} {}={} {}",
+                String::from(&cookie.domain),
+                String::from(&cookie.path),
+                cookie.name(),
+                cookie.value(),
+                expires
+            )?;
+        }
+        Ok(output)
+    }
+}
+
+fn parse_store(path: &Path, content: Option<&[u8]>) -> Result<CookieStore> {
+    match content {
+        Some(content) => CookieStore::load_json(BufReader::new(content))
+            .map_err(|e| anyhow!("failed to load cookie jar {}: {}", path.display(), e)),
+        None => Ok(CookieStore::default()),
+    }
+}
+
+fn cookie_key(cookie: &Cookie) -> CookieKey {
+    (
+        String::from(&cookie.domain),
+        String::from(&cookie.path),
+        cookie.name().to_string(),
+    )
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut store = self.store.write().unwrap();
+        for value in cookie_headers {
+            // a bad `set-cookie` from the server shouldn't fail the whole request
+            if let Ok(s) = value.to_str() {
+                let _ = store.parse(s, url);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let store = self.store.read().unwrap();
+        let value = store
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if value.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&value).ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore as _;
+
+    #[tokio::test]
+    async fn cookie_jar_should_persist_session_cookies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookies.json");
+        let url = Url::parse("http://localhost/login").unwrap();
+
+        let jar = CookieJar::load(&path).await.unwrap();
+        let value = HeaderValue::from_static("session=abc; Path=/");
+        jar.set_cookies(&mut std::iter::once(&value), &url);
+        jar.save().await.unwrap();
+
+        let jar = CookieJar::load(&path).await.unwrap();
+        let url = Url::parse("http://localhost/todo").unwrap();
+        assert_eq!(jar.cookies(&url).unwrap(), "session=abc");
+        assert_eq!(jar.get_text().unwrap(), "localhost / session=abc session\n");
+
+        jar.clear().await.unwrap();
+        assert!(jar.cookies(&url).is_none());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn cookie_jar_save_should_keep_cookies_saved_by_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookies.json");
+        let url = Url::parse("http://localhost/").unwrap();
+        let set = |jar: &CookieJar, value: &'static str| {
+            let value = HeaderValue::from_static(value);
+            jar.set_cookies(&mut std::iter::once(&value), &url);
+        };
+
+        let jar = CookieJar::load(&path).await.unwrap();
+        set(&jar, "old=1; Path=/");
+        jar.save().await.unwrap();
+
+        // both loaded before either saved
+        let jar1 = CookieJar::load(&path).await.unwrap();
+        let jar2 = CookieJar::load(&path).await.unwrap();
+        set(&jar1, "a=1; Path=/");
+        set(&jar2, "b=2; Path=/");
+        set(&jar2, "old=1; Path=/; Max-Age=0");
+        jar1.save().await.unwrap();
+        jar2.save().await.unwrap();
+
+        let jar = CookieJar::load(&path).await.unwrap();
+        let mut cookies: Vec<_> = jar
+            .cookies(&url)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .split("; ")
+            .map(String::from)
+            .collect();
+        cookies.sort();
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+    }
+}
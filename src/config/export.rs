@@ -0,0 +1,426 @@
+use super::{Auth, HttpVersion, Redirect, RedirectMode, RequestProfile, Secret, REDACTED};
+use crate::{cli::ExportFormat, ExtraArgs};
+use anyhow::{anyhow, Result};
+use reqwest::{
+    cookie::CookieStore,
+    header::{self, HeaderName, HeaderValue},
+    Client, Method, Request,
+};
+use std::fmt::Write;
+
+impl RequestProfile {
+    /// the request as it would be sent, printed as a command or snippet to run elsewhere
+    ///
+    /// the auth header, the cookies from the jar and the signature are all included, as
+    /// `<redacted>` unless `show_secrets` is set.
+    pub async fn export(
+        &self,
+        args: &ExtraArgs,
+        format: ExportFormat,
+        show_secrets: bool,
+    ) -> Result<String> {
+        let req = self.resolve_request(args).await?;
+        let secrets = Secrets(show_secrets);
+        match format {
+            ExportFormat::Curl => self.to_curl(&req, secrets),
+            ExportFormat::Httpie => self.to_httpie(&req, secrets),
+            ExportFormat::Reqwest => self.to_reqwest(&req, secrets),
+        }
+    }
+
+    async fn resolve_request(&self, args: &ExtraArgs) -> Result<Request> {
+        let (mut headers, query, body) = self.generate(args)?;
+        let url = self.request_url(args, &query)?;
+        self.compression
+            .clone()
+            .unwrap_or_default()
+            .apply(&mut headers)?;
+        if let Some(auth) = self.auth.as_ref() {
+            auth.apply(&mut headers, &self.client_builder().await?.build()?)
+                .await?;
+        }
+        if let Some(jar) = self.load_cookie_jar().await? {
+            if let Some(cookies) = jar.cookies(&url) {
+                headers.insert(header::COOKIE, cookies);
+            }
+        }
+        self.build_request(&Client::new(), url, headers, body)
+    }
+
+    fn to_curl(&self, req: &Request, secrets: Secrets) -> Result<String> {
+        let mut args = vec![];
+        if req.method() != Method::GET || has_body(req) {
+            args.push(format!("-X {}", req.method()));
+        }
+        args.push(quote(req.url().as_str()));
+        for (k, v) in req.headers() {
+            let value = secrets.header(k, v)?;
+            args.push(format!("-H {}", quote(&format!("{}: {}", k, value))));
+        }
+        if let Some(body) = body_text(req)? {
+            args.push(format!("--data-raw {}", quote(body)));
+        }
+        if let Some(Auth::Digest { username, password }) = self.auth.as_ref() {
+            let user = format!("{}:{}", username, secrets.show(password)?);
+            args.push(format!("--digest -u {}", quote(&user)));
+        }
+        // only an encoding that was asked for is decoded
+        if self.compression.clone().unwrap_or_default().decode
+            && req.headers().contains_key(header::ACCEPT_ENCODING)
+        {
+            args.push("--compressed".into());
+        }
+        match self.redirect.unwrap_or_default() {
+            Redirect::Mode(RedirectMode::None) => {}
+            redirect => args.push(format!("-L --max-redirs {}", redirect.max_hops())),
+        }
+        if let Some(timeout) = self.timeout {
+            if let Some(connect) = timeout.connect {
+                args.push(format!("--connect-timeout {}", connect.as_secs_f64()));
+            }
+            if let Some(total) = timeout.total {
+                args.push(format!("--max-time {}", total.as_secs_f64()));
+            }
+        }
+        let port = self.url.port_or_known_default().unwrap_or_default();
+        for (target, ip) in self.resolve.iter() {
+            let (host, target_port) = super::parse_resolve_target(target)?;
+            if target_port.is_none_or(|p| p == port) {
+                args.push(format!(
+                    "--resolve {}",
+                    quote(&format!("{}:{}:{}", host, port, ip))
+                ));
+            }
+        }
+        if let Some(socket) = self.socket.as_ref() {
+            args.push(format!(
+                "--unix-socket {}",
+                quote(&socket.to_string_lossy())
+            ));
+        }
+        if let Some(transport) = self.transport.as_ref() {
+            if let Some(path) = transport.ca_bundle.as_ref() {
+                args.push(format!("--cacert {}", quote(&path.to_string_lossy())));
+            }
+            if let Some(path) = transport.client_cert.as_ref() {
+                args.push(format!("--cert {}", quote(&path.to_string_lossy())));
+            }
+            if let Some(path) = transport.client_key.as_ref() {
+                args.push(format!("--key {}", quote(&path.to_string_lossy())));
+            }
+            if transport.pkcs12.is_some() {
+                return Err(anyhow!("pkcs12 client certificates can't be exported"));
+            }
+            if let Some(proxy) = transport.proxy.as_ref() {
+                args.push(format!("--proxy {}", quote(proxy.as_str())));
+            }
+            if !transport.no_proxy.is_empty() {
+                args.push(format!(
+                    "--noproxy {}",
+                    quote(&transport.no_proxy.join(","))
+                ));
+            }
+            if transport.is_insecure() {
+                args.push("-k".into());
+            }
+            match transport.http_version {
+                Some(HttpVersion::Http1) => args.push("--http1.1".into()),
+                Some(HttpVersion::Http2) => args.push("--http2-prior-knowledge".into()),
+                None => {}
+            }
+        }
+        Ok(format!("curl {}", args.join(" \\\n  ")))
+    }
+
+    fn to_httpie(&self, req: &Request, secrets: Secrets) -> Result<String> {
+        self.check_settings("httpie", false)?;
+        let mut args = vec!["--ignore-stdin".to_string()];
+        match self.redirect.unwrap_or_default() {
+            Redirect::Mode(RedirectMode::None) => {}
+            redirect => args.push(format!("--follow --max-redirects {}", redirect.max_hops())),
+        }
+        if let Some(total) = self.timeout.and_then(|t| t.total) {
+            args.push(format!("--timeout {}", total.as_secs_f64()));
+        }
+        if let Some(Auth::Digest { username, password }) = self.auth.as_ref() {
+            let user = format!("{}:{}", username, secrets.show(password)?);
+            args.push(format!("-A digest -a {}", quote(&user)));
+        }
+        if let Some(transport) = self.transport.as_ref() {
+            if let Some(proxy) = transport.proxy.as_ref() {
+                for scheme in ["http", "https"] {
+                    args.push(format!("--proxy {}:{}", scheme, quote(proxy.as_str())));
+                }
+            }
+            if transport.is_insecure() {
+                args.push("--verify no".into());
+            }
+        }
+        if let Some(body) = body_text(req)? {
+            args.push(format!("--raw {}", quote(body)));
+        }
+        args.push(req.method().to_string());
+        args.push(quote(req.url().as_str()));
+        for (k, v) in req.headers() {
+            args.push(quote(&format!("{}:{}", k, secrets.header(k, v)?)));
+        }
+        Ok(format!("http {}", args.join(" \\\n  ")))
+    }
+
+    fn to_reqwest(&self, req: &Request, secrets: Secrets) -> Result<String> {
+        self.check_settings("reqwest", true)?;
+        if let Some(Auth::Digest { .. }) = self.auth.as_ref() {
+            return Err(anyhow!("digest auth can't be exported to reqwest"));
+        }
+        let mut output = String::from("let client = reqwest::Client::builder()\n");
+        match self.redirect.unwrap_or_default() {
+            Redirect::Mode(RedirectMode::None) => {
+                writeln!(output, "    .redirect(reqwest::redirect::Policy::none())")?
+            }
+            redirect => writeln!(
+                output,
+                "    .redirect(reqwest::redirect::Policy::limited({}))",
+                redirect.max_hops()
+            )?,
+        }
+        if let Some(timeout) = self.timeout {
+            if let Some(connect) = timeout.connect {
+                writeln!(
+                    output,
+                    "    .connect_timeout(std::time::Duration::from_millis({}))",
+                    connect.as_millis()
+                )?;
+            }
+            if let Some(total) = timeout.total {
+                writeln!(
+                    output,
+                    "    .timeout(std::time::Duration::from_millis({}))",
+                    total.as_millis()
+                )?;
+            }
+        }
+        let port = self.url.port_or_known_default().unwrap_or_default();
+        for (target, ip) in self.resolve.iter() {
+            let (host, target_port) = super::parse_resolve_target(target)?;
+            if target_port.is_none_or(|p| p == port) {
+                let addr = std::net::SocketAddr::new(*ip, port).to_string();
+                writeln!(output, "    .resolve({:?}, {:?}.parse()?)", host, addr)?;
+            }
+        }
+        if let Some(transport) = self.transport.as_ref() {
+            if let Some(proxy) = transport.proxy.as_ref() {
+                writeln!(
+                    output,
+                    "    .proxy(reqwest::Proxy::all({:?})?)",
+                    proxy.as_str()
+                )?;
+            }
+            if transport.is_insecure() {
+                writeln!(output, "    .danger_accept_invalid_certs(true)")?;
+            }
+            match transport.http_version {
+                Some(HttpVersion::Http1) => writeln!(output, "    .http1_only()")?,
+                Some(HttpVersion::Http2) => writeln!(output, "    .http2_prior_knowledge()")?,
+                None => {}
+            }
+        }
+        writeln!(output, "    .build()?;")?;
+        writeln!(output, "let res = client")?;
+        writeln!(
+            output,
+            "    .request(reqwest::Method::from_bytes(b{:?})?, {:?})",
+            req.method().as_str(),
+            req.url().as_str()
+        )?;
+        for (k, v) in req.headers() {
+            let value = secrets.header(k, v)?;
+            writeln!(output, "    .header({:?}, {:?})", k.as_str(), value)?;
+        }
+        if let Some(body) = body_text(req)? {
+            writeln!(output, "    .body({:?})", body)?;
+        }
+        writeln!(output, "    .send()\n    .await?;")?;
+        Ok(output)
+    }
+
+    /// settings which only curl could reproduce
+    fn check_settings(&self, name: &str, resolve: bool) -> Result<()> {
+        let unsupported = match self.transport.as_ref() {
+            _ if self.socket.is_some() => Some("socket"),
+            _ if !resolve && !self.resolve.is_empty() => Some("resolve"),
+            Some(t) if t.ca_bundle.is_some() => Some("transport.ca_bundle"),
+            Some(t) if t.client_cert.is_some() || t.pkcs12.is_some() => Some("client certificates"),
+            Some(t) if !t.no_proxy.is_empty() => Some("transport.no_proxy"),
+            _ => None,
+        };
+        match unsupported {
+            Some(setting) => Err(anyhow!("{} can't be exported to {}", setting, name)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// whether the exported request shows the secrets it was resolved with
+#[derive(Debug, Clone, Copy)]
+struct Secrets(bool);
+
+impl Secrets {
+    /// the value of a header, auth, signatures and cookies are redacted
+    fn header<'a>(&self, name: &HeaderName, value: &'a HeaderValue) -> Result<&'a str> {
+        if !self.0 && (value.is_sensitive() || name == header::COOKIE) {
+            return Ok(REDACTED);
+        }
+        Ok(value.to_str()?)
+    }
+
+    fn show(&self, secret: &Secret) -> Result<String> {
+        match self.0 {
+            true => secret.resolve(),
+            false => Ok(REDACTED.to_string()),
+        }
+    }
+}
+
+fn has_body(req: &Request) -> bool {
+    req.body()
+        .and_then(|b| b.as_bytes())
+        .is_some_and(|b| !b.is_empty())
+}
+
+fn body_text(req: &Request) -> Result<Option<&str>> {
+    match req.body().and_then(|b| b.as_bytes()) {
+        Some(bytes) if !bytes.is_empty() => std::str::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| anyhow!("a binary body can't be exported")),
+        _ => Ok(None),
+    }
+}
+
+fn quote(s: &str) -> String {
+    shell_words::quote(s).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_curl, Secret};
+
+    #[tokio::test]
+    async fn export_curl_should_apply_overrides() {
+        let mut profile: RequestProfile = "http://localhost/todos?page=1".parse().unwrap();
+        profile.method = Method::POST;
+        profile.body = Some(serde_json::json!({"title": "hello"}));
+        profile.auth = Some(Auth::Bearer {
+            token: Secret::Value("t0ken".into()),
+        });
+        let args = ExtraArgs {
+            query: vec![("page".into(), "2".into())],
+            headers: vec![("x-tenant".into(), "acme".into())],
+            ..Default::default()
+        };
+        let output = profile
+            .export(&args, ExportFormat::Curl, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            "curl -X POST \\\n  'http://localhost/todos?page=2' \\\n  \
+             -H 'x-tenant: acme' \\\n  -H 'content-type: application/json' \\\n  \
+             -H 'authorization: Bearer t0ken' \\\n  \
+             --data-raw '{\"title\":\"hello\"}' \\\n  -L --max-redirs 10"
+        );
+    }
+
+    #[tokio::test]
+    async fn export_should_redact_secrets() {
+        let mut profile: RequestProfile = "http://localhost/todos".parse().unwrap();
+        profile.auth = Some(Auth::Digest {
+            username: "alice".into(),
+            password: Secret::Value("s3cret".into()),
+        });
+        profile
+            .headers
+            .insert(header::COOKIE, "session=abc".parse().unwrap());
+        profile.sign =
+            Some(serde_yaml::from_str("type: hmac\nkey: k3y\nheader: x-signature").unwrap());
+        for format in [
+            ExportFormat::Curl,
+            ExportFormat::Httpie,
+            ExportFormat::Reqwest,
+        ] {
+            let mut profile = profile.clone();
+            if format == ExportFormat::Reqwest {
+                // digest can't be exported to reqwest
+                profile.auth = None;
+            }
+            let output = profile
+                .export(&Default::default(), format, false)
+                .await
+                .unwrap();
+            assert!(!output.contains("s3cret"), "{}", output);
+            assert!(!output.contains("session=abc"), "{}", output);
+            assert!(output.contains("<redacted>"), "{}", output);
+        }
+
+        let output = profile
+            .export(&Default::default(), ExportFormat::Curl, true)
+            .await
+            .unwrap();
+        assert!(output.contains("alice:s3cret"));
+        assert!(output.contains("session=abc"));
+    }
+
+    #[tokio::test]
+    async fn export_curl_should_parse_back() {
+        let profile = parse_curl(
+            "curl -X PUT -H 'content-type: application/json' -d '{\"a\":[1,2]}' \
+             -k http://localhost/todos/1",
+        )
+        .unwrap();
+        let output = profile
+            .export(&Default::default(), ExportFormat::Curl, false)
+            .await
+            .unwrap();
+        let parsed = parse_curl(&output).unwrap();
+        assert_eq!(parsed.method, Method::PUT);
+        assert_eq!(parsed.url, profile.url);
+        assert_eq!(parsed.body, profile.body);
+        assert_eq!(parsed.transport, profile.transport);
+    }
+
+    #[tokio::test]
+    async fn export_reqwest_should_work() {
+        let mut profile: RequestProfile = "http://localhost/todos".parse().unwrap();
+        profile.compression = Some(crate::Compression {
+            accept: vec![],
+            decode: false,
+        });
+        let output = profile
+            .export(&Default::default(), ExportFormat::Reqwest, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            r#"let client = reqwest::Client::builder()
+    .redirect(reqwest::redirect::Policy::limited(10))
+    .build()?;
+let res = client
+    .request(reqwest::Method::from_bytes(b"GET")?, "http://localhost/todos")
+    .header("content-type", "application/json")
+    .body("{}")
+    .send()
+    .await?;
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn export_httpie_over_socket_should_fail() {
+        let mut profile: RequestProfile = "http://localhost/todos".parse().unwrap();
+        profile.socket = Some("/tmp/app.sock".into());
+        assert!(profile
+            .export(&Default::default(), ExportFormat::Httpie, false)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use serde_json::Value;
+use std::{io::Write, path::Path};
+
+/// encode a body object as `application/x-www-form-urlencoded`, arrays become repeated keys
+pub fn encode_urlencoded(body: &Value) -> Result<Vec<u8>> {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (name, value) in fields(body)? {
+        if is_file_part(value) {
+            return Err(anyhow!(
+                "file part {} is only supported in multipart/form-data",
+                name
+            ));
+        }
+        serializer.append_pair(name, &text_value(value)?);
+    }
+    Ok(serializer.finish().into_bytes())
+}
+
+/// encode a body object as `multipart/form-data` with the given boundary
+///
+/// a value like `{file: ./avatar.png, content_type: image/png}` is sent as a file part,
+/// the content type is guessed from the file name if not given.
+pub fn encode_multipart(body: &Value, boundary: &str) -> Result<Vec<u8>> {
+    let mut output = vec![];
+    for (name, value) in fields(body)? {
+        write!(&mut output, "--{}\r\n", boundary)?;
+        if is_file_part(value) {
+            let path = value["file"]
+                .as_str()
+                .ok_or_else(|| anyhow!("file of part {} must be a string", name))?;
+            let content = std::fs::read(path)
+                .with_context(|| format!("failed to read file {} of part {}", path, name))?;
+            let filename = Path::new(path)
+                .file_name()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let content_type = match value.get("content_type").and_then(|v| v.as_str()) {
+                Some(v) => v.to_string(),
+                None => mime_guess::from_path(path)
+                    .first_or_octet_stream()
+                    .to_string(),
+            };
+            write!(
+                &mut output,
+                "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+                escape_quote(name),
+                escape_quote(&filename)
+            )?;
+            write!(&mut output, "Content-Type: {}\r\n\r\n", content_type)?;
+            output.extend_from_slice(&content);
+        } else {
+            write!(
+                &mut output,
+                "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}",
+                escape_quote(name),
+                text_value(value)?
+            )?;
+        }
+        write!(&mut output, "\r\n")?;
+    }
+    write!(&mut output, "--{}--\r\n", boundary)?;
+    Ok(output)
+}
+
+pub fn generate_boundary() -> String {
+    format!(
+        "xdiff-{}",
+        hex::encode(rand::thread_rng().gen::<[u8; 12]>())
+    )
+}
+
+/// flatten the body object into (name, value) pairs, arrays are repeated
+fn fields(body: &Value) -> Result<Vec<(&str, &Value)>> {
+    let obj = body
+        .as_object()
+        .ok_or_else(|| anyhow!("form body must be an object"))?;
+    let mut fields = vec![];
+    for (name, value) in obj {
+        match value {
+            Value::Array(values) => fields.extend(values.iter().map(|v| (name.as_str(), v))),
+            v => fields.push((name.as_str(), v)),
+        }
+    }
+    Ok(fields)
+}
+
+fn is_file_part(value: &Value) -> bool {
+    value.get("file").is_some()
+}
+
+fn text_value(value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Null => Ok(String::new()),
+        Value::Bool(_) | Value::Number(_) => Ok(value.to_string()),
+        v => Err(anyhow!("nested value is not supported in a form: {}", v)),
+    }
+}
+
+fn escape_quote(s: &str) -> String {
+    s.replace('"', "%22")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn encode_urlencoded_should_work() {
+        let body = json!({"title": "hello world", "tags": ["a", "b"], "done": false});
+        assert_eq!(
+            String::from_utf8(encode_urlencoded(&body).unwrap()).unwrap(),
+            "done=false&tags=a&tags=b&title=hello+world"
+        );
+    }
+
+    #[test]
+    fn encode_multipart_with_file_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("avatar.png");
+        std::fs::write(&path, b"png").unwrap();
+
+        let body = json!({
+            "avatar": {"file": path.to_str().unwrap()},
+            "name": "kyros",
+        });
+        let output = encode_multipart(&body, "boundary").unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar.png\"\r\n\
+             Content-Type: image/png\r\n\r\n\
+             png\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+             kyros\r\n\
+             --boundary--\r\n"
+        );
+    }
+
+    #[test]
+    fn file_part_in_urlencoded_should_fail() {
+        let body = json!({"avatar": {"file": "./avatar.png"}});
+        assert!(encode_urlencoded(&body).is_err());
+    }
+}
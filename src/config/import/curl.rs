@@ -0,0 +1,569 @@
+use super::{push_field, set_text_body, Imported, ImportedRequest};
+use crate::config::{
+    query_params, Auth, Compression, Encoding, HttpVersion, Redirect, RedirectMode, RequestProfile,
+    Secret, Timeouts, Transport, ValidateConfig,
+};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Method, Url,
+};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, net::IpAddr, path::PathBuf, str::FromStr, time::Duration};
+
+/// turn a curl command, e.g. one copied from the browser devtools, into a profile
+pub fn parse_curl(command: &str) -> Result<RequestProfile> {
+    parse(command).map(|(profile, _)| profile)
+}
+
+/// the profile of a curl command, with what the user has to set up for it as warnings
+pub(crate) fn import_curl(name: &str, command: &str) -> Result<Imported> {
+    let (profile, warnings) = parse(command)?;
+    Ok(Imported {
+        requests: vec![ImportedRequest {
+            name: name.to_string(),
+            profile,
+            profile2: None,
+            response: None,
+        }],
+        warnings: warnings
+            .into_iter()
+            .map(|w| format!("{}: {}", name, w))
+            .collect(),
+        ..Default::default()
+    })
+}
+
+fn parse(command: &str) -> Result<(RequestProfile, Vec<String>)> {
+    // line continuations of multi-line commands
+    let command = command.replace("\\\r\n", " ").replace("\\\n", " ");
+    let mut args = shell_words::split(&command)
+        .context("invalid curl command")?
+        .into_iter();
+    match args.next() {
+        Some(cmd) if cmd == "curl" => {}
+        _ => return Err(anyhow!("not a curl command: {}", command)),
+    }
+
+    let mut curl = Curl::default();
+    while let Some(arg) = args.next() {
+        let (name, attached) = split_option(&arg);
+        let name = match name {
+            Some(name) => name,
+            None => {
+                curl.url = Some(arg);
+                continue;
+            }
+        };
+        if let Some(flags) = combined_flags(name, attached) {
+            for flag in flags {
+                curl.flag(&flag)?;
+            }
+            continue;
+        }
+        if takes_value(name) {
+            let value = match attached {
+                Some(v) => v.to_string(),
+                None => args
+                    .next()
+                    .ok_or_else(|| anyhow!("curl option {} needs a value", name))?,
+            };
+            curl.option(name, value)?;
+        } else {
+            curl.flag(name)?;
+        }
+    }
+    curl.into_profile()
+}
+
+/// the options of a curl command which matter for the request
+#[derive(Debug, Default)]
+struct Curl {
+    url: Option<String>,
+    method: Option<Method>,
+    headers: Vec<(String, String)>,
+    data: Vec<Data>,
+    form: Vec<(String, Value)>,
+    user: Option<String>,
+    digest: bool,
+    get: bool,
+    head: bool,
+    json: bool,
+    compressed: bool,
+    location: bool,
+    transport: Transport,
+    timeout: Timeouts,
+    max_redirs: Option<usize>,
+    socket: Option<PathBuf>,
+    resolve: BTreeMap<String, IpAddr>,
+}
+
+#[derive(Debug)]
+enum Data {
+    Text(String),
+    File(PathBuf),
+}
+
+impl Curl {
+    fn option(&mut self, name: &str, value: String) -> Result<()> {
+        match name {
+            "-X" | "--request" => self.method = Some(Method::from_str(&value.to_uppercase())?),
+            "-H" | "--header" => {
+                let (k, v) = match value.split_once(':') {
+                    Some((k, v)) => (k, v),
+                    // `-H 'x-empty;'` sends an empty header
+                    None => (value.trim_end_matches(';'), ""),
+                };
+                self.headers
+                    .push((k.trim().to_string(), v.trim().to_string()));
+            }
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => match value.strip_prefix('@') {
+                Some(path) => self.data.push(Data::File(path.into())),
+                None => self.data.push(Data::Text(value)),
+            },
+            "--data-raw" => self.data.push(Data::Text(value)),
+            "--data-urlencode" => self.data.push(Data::Text(urlencode_data(&value)?)),
+            "--json" => {
+                self.json = true;
+                self.data.push(Data::Text(value));
+            }
+            "-F" | "--form" | "--form-string" => {
+                let (k, v) = value
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("invalid form field: {}", value))?;
+                let v = match v.strip_prefix('@') {
+                    Some(file) if name != "--form-string" => file_part(file),
+                    _ if v.starts_with('<') && name != "--form-string" => {
+                        return Err(anyhow!("form fields read from a file are not supported"))
+                    }
+                    _ => json!(v),
+                };
+                self.form.push((k.to_string(), v));
+            }
+            "-u" | "--user" => self.user = Some(value),
+            "-b" | "--cookie" if value.contains('=') => {
+                self.headers.push(("cookie".into(), value));
+            }
+            "-b" | "--cookie" => return Err(anyhow!("cookie files are not supported")),
+            "-A" | "--user-agent" => self.headers.push(("user-agent".into(), value)),
+            "-e" | "--referer" => self.headers.push(("referer".into(), value)),
+            "--url" => self.url = Some(value),
+            "-m" | "--max-time" => self.timeout.total = Some(parse_seconds(&value)?),
+            "--connect-timeout" => self.timeout.connect = Some(parse_seconds(&value)?),
+            "--proxy" | "-x" => self.transport.proxy = Some(value.parse()?),
+            "--cacert" => self.transport.ca_bundle = Some(value.into()),
+            "-E" | "--cert" => self.transport.client_cert = Some(value.into()),
+            "--key" => self.transport.client_key = Some(value.into()),
+            "--noproxy" => {
+                let hosts = value.split(',').map(|h| h.trim().to_string());
+                self.transport.no_proxy.extend(hosts);
+            }
+            "--max-redirs" => self.max_redirs = Some(value.parse()?),
+            "--unix-socket" => self.socket = Some(value.into()),
+            "--resolve" => {
+                // host:port:addr, the addr could be an ipv6 one
+                let mut parts = value.splitn(3, ':');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(host), Some(port), Some(addr)) => {
+                        let addr = addr.trim_start_matches('[').trim_end_matches(']');
+                        self.resolve
+                            .insert(format!("{}:{}", host, port), addr.parse()?);
+                    }
+                    _ => return Err(anyhow!("invalid --resolve: {}", value)),
+                }
+            }
+            // only change what is printed
+            "-o" | "--output" | "-w" | "--write-out" => {}
+            _ => return Err(anyhow!("unsupported curl option: {}", name)),
+        }
+        Ok(())
+    }
+
+    fn flag(&mut self, name: &str) -> Result<()> {
+        match name {
+            "-G" | "--get" => self.get = true,
+            "-I" | "--head" => self.head = true,
+            "-k" | "--insecure" => self.transport.insecure = Some(true),
+            "--digest" => self.digest = true,
+            "--http1.1" => self.transport.http_version = Some(HttpVersion::Http1),
+            "--http2-prior-knowledge" => self.transport.http_version = Some(HttpVersion::Http2),
+            "--compressed" => self.compressed = true,
+            "-L" | "--location" => self.location = true,
+            "--basic" | "--http2" => {}
+            // only change what is printed
+            "-s" | "--silent" | "-S" | "--show-error" | "-v" | "--verbose" | "-i" | "--include"
+            | "-f" | "--fail" | "-N" | "--no-buffer" => {}
+            _ => return Err(anyhow!("unsupported curl option: {}", name)),
+        }
+        Ok(())
+    }
+
+    fn into_profile(self) -> Result<(RequestProfile, Vec<String>)> {
+        let mut warnings = vec![];
+        let url = self
+            .url
+            .ok_or_else(|| anyhow!("no url in the curl command"))?;
+        // like curl, a url without a scheme is http
+        let url = match url.contains("://") {
+            true => url,
+            false => format!("http://{}", url),
+        };
+        let mut url = Url::parse(&url)?;
+        let mut params = query_params(&url);
+        url.set_query(None);
+
+        let mut headers = HeaderMap::new();
+        for (k, v) in self.headers.iter() {
+            headers.append(HeaderName::from_str(k)?, HeaderValue::from_str(v)?);
+        }
+
+        let has_body = !self.data.is_empty() || !self.form.is_empty();
+        let method = match self.method {
+            Some(method) => method,
+            None if self.head => Method::HEAD,
+            None if has_body && !self.get => Method::POST,
+            None => Method::GET,
+        };
+        let mut profile = RequestProfile::new(method, url, None, HeaderMap::new(), None);
+
+        if !self.form.is_empty() {
+            if !self.data.is_empty() {
+                return Err(anyhow!("-d and -F can't be used together"));
+            }
+            set_default(&mut headers, header::CONTENT_TYPE, "multipart/form-data");
+            let mut body = json!({});
+            for (k, v) in self.form {
+                push_field(&mut body, &k, v);
+            }
+            profile.body = Some(body);
+        } else if self.get {
+            let data = join_text(self.data)?;
+            let query: Url = format!("http://localhost/?{}", data).parse()?;
+            for (k, v) in query_params(&query).as_object().unwrap() {
+                params[k] = v.clone();
+            }
+        } else if self.json {
+            set_default(&mut headers, header::CONTENT_TYPE, "application/json");
+            set_default(&mut headers, header::ACCEPT, "application/json");
+            set_body(&mut profile, &headers, self.data)?;
+        } else if !self.data.is_empty() {
+            set_default(
+                &mut headers,
+                header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            );
+            set_body(&mut profile, &headers, self.data)?;
+        }
+
+        profile.params = Some(params);
+        profile.headers = headers;
+        profile.auth = match (self.user, self.digest) {
+            (None, _) => None,
+            (Some(user), digest) => {
+                // the password is read from the env, so it isn't kept in the config
+                let (username, password) = match user.split_once(':') {
+                    Some((u, _)) => {
+                        let env = password_env(u);
+                        warnings.push(format!("set the env var {} to the password of {}", env, u));
+                        (u.to_string(), Some(Secret::Env { env }))
+                    }
+                    None => (user, None),
+                };
+                match (digest, password) {
+                    (true, Some(password)) => Some(Auth::Digest { username, password }),
+                    (true, None) => return Err(anyhow!("digest auth needs a password")),
+                    (false, password) => Some(Auth::Basic { username, password }),
+                }
+            }
+        };
+        if self.transport != Transport::default() {
+            profile.transport = Some(self.transport);
+        }
+        if self.timeout != Timeouts::default() {
+            profile.timeout = Some(self.timeout);
+        }
+        // like curl, redirects are only followed with `-L`
+        profile.redirect = match self.location {
+            true => self.max_redirs.map(Redirect::Max),
+            false => Some(Redirect::Mode(RedirectMode::None)),
+        };
+        if self.compressed {
+            profile.compression = Some(Compression {
+                accept: vec![Encoding::Gzip, Encoding::Deflate, Encoding::Br],
+                decode: true,
+            });
+        }
+        profile.socket = self.socket;
+        profile.resolve = self.resolve;
+        profile.validate()?;
+        Ok((profile, warnings))
+    }
+}
+
+/// `alice` gives `ALICE_PASSWORD`
+fn password_env(username: &str) -> String {
+    let name: String = username
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+    match name.is_empty() {
+        true => "PASSWORD".to_string(),
+        false => format!("{}_PASSWORD", name),
+    }
+}
+
+/// `--name` or `-n`, with the value of `-nvalue` if attached; `None` for an argument
+fn split_option(arg: &str) -> (Option<&str>, Option<&str>) {
+    if arg.starts_with("--") {
+        (Some(arg), None)
+    } else if arg.starts_with('-') && arg.len() > 2 {
+        (Some(&arg[..2]), Some(&arg[2..]))
+    } else if arg.starts_with('-') && arg.len() == 2 {
+        (Some(arg), None)
+    } else {
+        (None, None)
+    }
+}
+
+/// `-sSL` is `-s -S -L`
+fn combined_flags(name: &str, attached: Option<&str>) -> Option<Vec<String>> {
+    let attached = attached?;
+    if takes_value(name) {
+        return None;
+    }
+    let flags = std::iter::once(name.to_string())
+        .chain(attached.chars().map(|c| format!("-{}", c)))
+        .collect();
+    Some(flags)
+}
+
+fn takes_value(name: &str) -> bool {
+    matches!(
+        name,
+        "-X" | "--request"
+            | "-H"
+            | "--header"
+            | "-d"
+            | "--data"
+            | "--data-ascii"
+            | "--data-binary"
+            | "--data-raw"
+            | "--data-urlencode"
+            | "--json"
+            | "-F"
+            | "--form"
+            | "--form-string"
+            | "-u"
+            | "--user"
+            | "-b"
+            | "--cookie"
+            | "-A"
+            | "--user-agent"
+            | "-e"
+            | "--referer"
+            | "--url"
+            | "-m"
+            | "--max-time"
+            | "--connect-timeout"
+            | "-x"
+            | "--proxy"
+            | "--cacert"
+            | "-E"
+            | "--cert"
+            | "--key"
+            | "--noproxy"
+            | "--max-redirs"
+            | "--unix-socket"
+            | "--resolve"
+            | "-o"
+            | "--output"
+            | "-w"
+            | "--write-out"
+    )
+}
+
+/// `content`, `=content`, `name=content`, `@file` or `name@file`, as curl encodes them
+fn urlencode_data(value: &str) -> Result<String> {
+    let encode = |s: &str| url::form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+    let read = |path: &str| {
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))
+    };
+    Ok(match (value.split_once('='), value.split_once('@')) {
+        (Some(("", content)), _) => encode(content),
+        (Some((name, content)), _) => format!("{}={}", name, encode(content)),
+        (None, Some(("", path))) => encode(&read(path)?),
+        (None, Some((name, path))) => format!("{}={}", name, encode(&read(path)?)),
+        (None, None) => encode(value),
+    })
+}
+
+/// `@avatar.png;type=image/png` becomes a file part
+fn file_part(value: &str) -> Value {
+    let mut parts = value.split(';');
+    let mut part = json!({ "file": parts.next().unwrap_or_default() });
+    for attr in parts {
+        if let Some(content_type) = attr.trim().strip_prefix("type=") {
+            part["content_type"] = json!(content_type);
+        }
+    }
+    part
+}
+
+fn join_text(data: Vec<Data>) -> Result<String> {
+    data.into_iter()
+        .map(|d| match d {
+            Data::Text(s) => Ok(s),
+            Data::File(path) => std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display())),
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(|v| v.join("&"))
+}
+
+/// a single `@file` is sent from the file, anything else is joined into a text body
+fn set_body(profile: &mut RequestProfile, headers: &HeaderMap, mut data: Vec<Data>) -> Result<()> {
+    if let [Data::File(_)] = data.as_slice() {
+        if let Some(Data::File(path)) = data.pop() {
+            profile.body_file = Some(path);
+        }
+        return Ok(());
+    }
+    set_text_body(profile, headers, join_text(data)?);
+    Ok(())
+}
+
+fn set_default(headers: &mut HeaderMap, name: HeaderName, value: &'static str) {
+    if !headers.contains_key(&name) {
+        headers.insert(name, HeaderValue::from_static(value));
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration> {
+    let secs: f64 = s
+        .parse()
+        .with_context(|| format!("invalid seconds: {}", s))?;
+    Ok(Duration::from_secs_f64(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_curl_with_json_body_should_work() {
+        let profile = parse_curl(
+            r#"curl 'https://api.example.com/todos?page=2' \
+              -X PUT -H 'Content-Type: application/json' -H 'x-tenant: acme' \
+              --data-raw '{"title":"hello","done":false}' --compressed -sSL"#,
+        )
+        .unwrap();
+        assert_eq!(profile.method, Method::PUT);
+        assert_eq!(profile.url.as_str(), "https://api.example.com/todos");
+        assert_eq!(profile.params, Some(json!({"page": 2})));
+        assert_eq!(profile.headers["x-tenant"], "acme");
+        assert_eq!(profile.body, Some(json!({"title": "hello", "done": false})));
+        assert_eq!(profile.redirect, None);
+        assert_eq!(
+            profile.compression.unwrap().accept,
+            vec![Encoding::Gzip, Encoding::Deflate, Encoding::Br]
+        );
+    }
+
+    #[test]
+    fn parse_curl_with_form_data_should_work() {
+        let profile = parse_curl(
+            "curl -u kyros:secret -d title=hello -d tags=a -d tags=b \
+             --data-urlencode 'note=hi there' http://localhost/todos",
+        )
+        .unwrap();
+        assert_eq!(profile.method, Method::POST);
+        assert_eq!(
+            profile.headers["content-type"],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(
+            profile.body,
+            Some(json!({"title": "hello", "tags": ["a", "b"], "note": "hi there"}))
+        );
+        assert_eq!(
+            profile.auth,
+            Some(Auth::Basic {
+                username: "kyros".into(),
+                password: Some(Secret::Env {
+                    env: "KYROS_PASSWORD".into()
+                })
+            })
+        );
+
+        let imported = import_curl("todo", "curl -u kyros:secret http://localhost/").unwrap();
+        assert_eq!(
+            imported.warnings,
+            vec!["todo: set the env var KYROS_PASSWORD to the password of kyros"]
+        );
+    }
+
+    #[test]
+    fn parse_curl_with_multipart_should_work() {
+        let profile = parse_curl(
+            "curl -F name=kyros -F 'avatar=@./avatar.png;type=image/png' localhost:8080/users",
+        )
+        .unwrap();
+        assert_eq!(profile.url.as_str(), "http://localhost:8080/users");
+        assert_eq!(profile.headers["content-type"], "multipart/form-data");
+        assert_eq!(
+            profile.body,
+            Some(json!({
+                "name": "kyros",
+                "avatar": {"file": "./avatar.png", "content_type": "image/png"}
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_curl_with_get_data_should_use_query() {
+        let profile =
+            parse_curl("curl -G -d status=open -d page=1 http://localhost/todos?a=1").unwrap();
+        assert_eq!(profile.method, Method::GET);
+        assert_eq!(
+            profile.params,
+            Some(json!({"a": 1, "status": "open", "page": 1}))
+        );
+        assert!(profile.body.is_none());
+        assert_eq!(profile.redirect, Some(Redirect::Mode(RedirectMode::None)));
+        assert!(profile.compression.is_none());
+    }
+
+    #[test]
+    fn parse_curl_with_connection_options_should_work() {
+        let profile = parse_curl(
+            "curl -L --max-redirs 3 --resolve api.example.com:443:127.0.0.1 \
+             --unix-socket /tmp/app.sock --max-time 2.5 https://api.example.com/",
+        )
+        .unwrap();
+        assert_eq!(profile.redirect, Some(Redirect::Max(3)));
+        assert_eq!(
+            profile.resolve["api.example.com:443"],
+            "127.0.0.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(profile.socket, Some(PathBuf::from("/tmp/app.sock")));
+        assert_eq!(
+            profile.timeout.unwrap().total,
+            Some(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn parse_curl_with_unknown_option_should_fail() {
+        assert!(parse_curl("curl --doh-url https://dns http://localhost").is_err());
+        assert!(parse_curl("wget http://localhost").is_err());
+    }
+
+    #[test]
+    fn request_profile_from_str_should_take_curl() {
+        let profile: RequestProfile = "curl -XDELETE http://localhost/todos/1".parse().unwrap();
+        assert_eq!(profile.method, Method::DELETE);
+    }
+}
@@ -0,0 +1,353 @@
+use super::{profile_name, push_field, set_text_body, unique_name, ImportedRequest};
+use crate::config::{query_params, ResponseExt, ValidateConfig};
+use crate::{RequestProfile, ResponseProfile};
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Method, Response, Url,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::HashSet, str::FromStr};
+
+/// Which entries of a HAR file to import, and what to leave out of them
+#[derive(Debug, Clone, Default)]
+pub struct HarFilter {
+    /// a url pattern, `*` matches anything, without a `*` it matches a part of the url
+    pub url: Option<String>,
+    /// keep only these methods, all of them if empty
+    pub methods: Vec<Method>,
+    /// headers to leave out, e.g. `cookie` or `user-agent`
+    pub strip_headers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarLog {
+    entries: Vec<HarRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarRecord {
+    request: HarRequest,
+    response: Option<HarResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<HarPair>,
+    post_data: Option<HarPostData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarPostData {
+    #[serde(default)]
+    mime_type: String,
+    text: Option<String>,
+    #[serde(default)]
+    params: Vec<HarParam>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarParam {
+    name: String,
+    value: Option<String>,
+    file_name: Option<String>,
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    #[serde(default)]
+    http_version: String,
+    #[serde(default)]
+    headers: Vec<HarPair>,
+    content: Option<HarContent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarContent {
+    text: Option<String>,
+    encoding: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarPair {
+    name: String,
+    value: String,
+}
+
+/// headers which belong to the recorded connection rather than to the request
+const CONNECTION_HEADERS: [&str; 4] = ["host", "content-length", "connection", "keep-alive"];
+
+/// the entries of a HAR file which pass the filter, in the order they were recorded
+pub async fn parse_har(content: &str, filter: &HarFilter) -> Result<Vec<ImportedRequest>> {
+    let har: Har = serde_json::from_str(content).context("invalid HAR file")?;
+    let mut names = HashSet::new();
+    let mut entries = vec![];
+    for (idx, record) in har.log.entries.into_iter().enumerate() {
+        let req = &record.request;
+        if !req.url.starts_with("http://") && !req.url.starts_with("https://") {
+            continue;
+        }
+        let method = Method::from_str(&req.method.to_uppercase())?;
+        if !filter.methods.is_empty() && !filter.methods.contains(&method) {
+            continue;
+        }
+        if let Some(pattern) = filter.url.as_deref() {
+            if !url_matches(pattern, &req.url) {
+                continue;
+            }
+        }
+        let profile = to_profile(method, req, filter)
+            .with_context(|| format!("failed to import entry {}: {}", idx + 1, req.url))?;
+        let response = match record.response {
+            Some(res) if res.status > 0 => Some(response_text(res, &profile.url).await?),
+            _ => None,
+        };
+        let name = unique_name(&mut names, &profile_name(&profile));
+        entries.push(ImportedRequest {
+            name,
+            profile,
+            profile2: None,
+            response,
+        });
+    }
+    Ok(entries)
+}
+
+fn to_profile(method: Method, req: &HarRequest, filter: &HarFilter) -> Result<RequestProfile> {
+    let mut url = Url::parse(&req.url)?;
+    let params = query_params(&url);
+    url.set_query(None);
+    url.set_fragment(None);
+
+    let mut headers = HeaderMap::new();
+    for h in req.headers.iter() {
+        let name = h.name.to_lowercase();
+        // pseudo headers of http/2 captures, e.g. `:authority`
+        if name.starts_with(':')
+            || CONNECTION_HEADERS.contains(&name.as_str())
+            || filter
+                .strip_headers
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(&name))
+        {
+            continue;
+        }
+        headers.append(
+            HeaderName::from_str(&name)?,
+            HeaderValue::from_str(&h.value)?,
+        );
+    }
+
+    let mut profile = RequestProfile::new(method, url, Some(params), HeaderMap::new(), None);
+    if let Some(data) = req.post_data.as_ref() {
+        if !headers.contains_key(header::CONTENT_TYPE) && !data.mime_type.is_empty() {
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_str(&data.mime_type)?,
+            );
+        }
+        match (data.text.as_ref(), data.params.is_empty()) {
+            (Some(text), _) if !text.is_empty() => {
+                set_text_body(&mut profile, &headers, text.clone())
+            }
+            (_, false) => profile.body = Some(params_body(&data.params)),
+            _ => {}
+        }
+    }
+    profile.headers = headers;
+    profile.validate()?;
+    Ok(profile)
+}
+
+/// form params without the raw text, files become file parts
+fn params_body(params: &[HarParam]) -> Value {
+    let mut body = json!({});
+    for p in params {
+        let value = match p.file_name.as_ref() {
+            Some(file) => {
+                let mut part = json!({ "file": file });
+                if let Some(content_type) = p.content_type.as_ref() {
+                    part["content_type"] = json!(content_type);
+                }
+                part
+            }
+            None => json!(p.value.clone().unwrap_or_default()),
+        };
+        push_field(&mut body, &p.name, value);
+    }
+    body
+}
+
+async fn response_text(res: HarResponse, url: &Url) -> Result<String> {
+    let version = match res.http_version.to_lowercase().as_str() {
+        "http/1.0" => http::Version::HTTP_10,
+        "h2" | "http/2" | "http/2.0" => http::Version::HTTP_2,
+        "h3" | "http/3" | "http/3.0" => http::Version::HTTP_3,
+        _ => http::Version::HTTP_11,
+    };
+    let mut builder = http::Response::builder()
+        .status(res.status)
+        .version(version);
+    for h in res.headers.iter() {
+        let name = h.name.to_lowercase();
+        // the recorded content is already decoded
+        if name.starts_with(':') || name == "content-encoding" || name == "content-length" {
+            continue;
+        }
+        builder = builder.header(name, h.value.as_str());
+    }
+    let body = match res.content {
+        Some(HarContent {
+            text: Some(text),
+            encoding,
+        }) => match encoding.as_deref() {
+            Some("base64") => base64::decode(text.trim())?,
+            _ => text.into_bytes(),
+        },
+        _ => vec![],
+    };
+    let res = ResponseExt {
+        res: Response::from(builder.body(body)?),
+        url: url.clone(),
+        attempts: 1,
+        redirects: vec![],
+        encoding: None,
+    };
+    res.get_text(&ResponseProfile::snapshot()).await
+}
+
+/// `*` matches any text, a pattern without `*` matches a part of the url
+fn url_matches(pattern: &str, url: &str) -> bool {
+    if !pattern.contains('*') {
+        return url.contains(pattern);
+    }
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !url.starts_with(first) || url.len() < first.len() + last.len() || !url.ends_with(last) {
+        return false;
+    }
+    let mut rest = &url[first.len()..url.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{"log": {"entries": [
+        {
+            "request": {
+                "method": "GET",
+                "url": "https://api.example.com/todos/1?expand=user",
+                "httpVersion": "h2",
+                "headers": [
+                    {"name": ":authority", "value": "api.example.com"},
+                    {"name": "accept", "value": "application/json"},
+                    {"name": "cookie", "value": "sid=abc"},
+                    {"name": "user-agent", "value": "Mozilla/5.0"}
+                ]
+            },
+            "response": {
+                "status": 200,
+                "httpVersion": "h2",
+                "headers": [
+                    {"name": "date", "value": "Mon, 19 Oct 2026 10:00:00 GMT"},
+                    {"name": "content-type", "value": "application/json"},
+                    {"name": "content-encoding", "value": "br"},
+                    {"name": "etag", "value": "\"v1\""}
+                ],
+                "content": {"text": "eyJpZCI6MX0=", "encoding": "base64"}
+            }
+        },
+        {
+            "request": {
+                "method": "POST",
+                "url": "https://api.example.com/todos",
+                "headers": [{"name": "Content-Type", "value": "application/x-www-form-urlencoded"}],
+                "postData": {"mimeType": "application/x-www-form-urlencoded", "text": "title=hi&done=false"}
+            },
+            "response": {"status": 0, "headers": []}
+        },
+        {
+            "request": {"method": "GET", "url": "https://cdn.example.com/app.js", "headers": []}
+        },
+        {
+            "request": {"method": "GET", "url": "https://api.example.com/todos/2", "headers": []}
+        }
+    ]}}"#;
+
+    #[tokio::test]
+    async fn parse_har_should_work() {
+        let filter = HarFilter {
+            url: Some("https://api.example.com/*".into()),
+            methods: vec![],
+            strip_headers: vec!["cookie".into(), "user-agent".into()],
+        };
+        let entries = parse_har(HAR, &filter).await.unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["get_todos_1", "post_todos", "get_todos_2"]);
+
+        let get = &entries[0];
+        assert_eq!(get.profile.url.as_str(), "https://api.example.com/todos/1");
+        assert_eq!(get.profile.params, Some(json!({"expand": "user"})));
+        let headers: Vec<_> = get.profile.headers.keys().map(|k| k.as_str()).collect();
+        assert_eq!(headers, vec!["accept"]);
+        assert_eq!(
+            get.response.as_deref(),
+            Some("200 OKcontent-type: \"application/json\"\n\n{\n  \"id\": 1\n}")
+        );
+
+        let post = &entries[1];
+        assert_eq!(
+            post.profile.body,
+            Some(json!({"title": "hi", "done": "false"}))
+        );
+        assert!(post.response.is_none());
+    }
+
+    #[tokio::test]
+    async fn parse_har_should_filter_methods() {
+        let filter = HarFilter {
+            methods: vec![Method::POST],
+            ..Default::default()
+        };
+        let entries = parse_har(HAR, &filter).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].profile.method, Method::POST);
+    }
+
+    #[test]
+    fn url_matches_should_work() {
+        assert!(url_matches("/todos", "https://api.example.com/todos/1"));
+        assert!(url_matches(
+            "https://*.example.com/*/1",
+            "https://api.example.com/todos/1"
+        ));
+        assert!(!url_matches(
+            "https://*.example.com/*/2",
+            "https://api.example.com/todos/1"
+        ));
+        assert!(!url_matches("*.js", "https://api.example.com/todos/1"));
+    }
+}
@@ -0,0 +1,214 @@
+mod curl;
+mod har;
+mod openapi;
+mod postman;
+
+use curl::import_curl;
+pub use curl::parse_curl;
+pub use har::{parse_har, HarFilter};
+pub use openapi::parse_openapi;
+pub(crate) use openapi::resolve;
+pub use postman::parse_postman;
+
+use super::decode_braces;
+use crate::{cli::ImportSource, RequestProfile};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{self, HeaderMap},
+    Method, Url,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+    str::FromStr,
+};
+
+/// A request read from another tool
+#[derive(Debug)]
+pub struct ImportedRequest {
+    pub name: String,
+    pub profile: RequestProfile,
+    /// the same request on the second server, if the source knows one
+    pub profile2: Option<RequestProfile>,
+    /// the recorded response, in the same text as the diff of xdiff, if there is one
+    pub response: Option<String>,
+}
+
+/// What an import source gave
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub requests: Vec<ImportedRequest>,
+    /// values for the `{{name}}` variables of the requests, for the `defaults` of the config
+    pub vars: BTreeMap<String, String>,
+    /// what couldn't be imported
+    pub warnings: Vec<String>,
+}
+
+impl From<Vec<ImportedRequest>> for Imported {
+    fn from(requests: Vec<ImportedRequest>) -> Self {
+        Self {
+            requests,
+            ..Default::default()
+        }
+    }
+}
+
+/// read the requests of an import source, writing the recorded responses if asked to
+pub async fn import_requests(source: &ImportSource) -> Result<Imported> {
+    let mut imported = match source {
+        ImportSource::Curl(args) => import_curl(&args.profile, &args.command),
+        ImportSource::Har(args) => {
+            let content = tokio::fs::read_to_string(&args.file)
+                .await
+                .with_context(|| format!("failed to read {}", args.file.display()))?;
+            let filter = HarFilter {
+                url: args.url.clone(),
+                methods: args
+                    .method
+                    .iter()
+                    .map(|m| Method::from_str(&m.to_uppercase()))
+                    .collect::<Result<_, _>>()?,
+                strip_headers: args.strip_header.clone(),
+            };
+            let requests = parse_har(&content, &filter).await?;
+            if let Some(dir) = args.snapshot_dir.as_ref() {
+                write_snapshots(dir, &requests).await?;
+            }
+            Ok(requests.into())
+        }
+        ImportSource::Openapi(args) => {
+            let content = tokio::fs::read_to_string(&args.file)
+                .await
+                .with_context(|| format!("failed to read {}", args.file.display()))?;
+            parse_openapi(&content, args.server.as_deref(), args.server2.as_deref()).map(Into::into)
+        }
+        ImportSource::Postman(args) => {
+            let content = tokio::fs::read_to_string(&args.file)
+                .await
+                .with_context(|| format!("failed to read {}", args.file.display()))?;
+            let environment = match args.environment.as_ref() {
+                Some(path) => Some(
+                    tokio::fs::read_to_string(path)
+                        .await
+                        .with_context(|| format!("failed to read {}", path.display()))?,
+                ),
+                None => None,
+            };
+            parse_postman(&content, environment.as_deref())
+        }
+    }?;
+    for req in imported.requests.iter() {
+        if req
+            .profile
+            .auth
+            .as_ref()
+            .is_some_and(|a| a.has_inline_secret())
+        {
+            imported.warnings.push(format!(
+                "{}: the auth secret is redacted in the printed config, set it with env or file",
+                req.name
+            ));
+        }
+    }
+    Ok(imported)
+}
+
+/// one `(name).txt` per request with a recorded response
+async fn write_snapshots(dir: &Path, requests: &[ImportedRequest]) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    for req in requests {
+        if let Some(response) = req.response.as_ref() {
+            let path = dir.join(format!("{}.txt", req.name));
+            tokio::fs::write(&path, response)
+                .await
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// the same request sent to another server, only the scheme, host and port are changed
+pub fn rebase(profile: &RequestProfile, base: &Url) -> Result<RequestProfile> {
+    let mut profile = profile.clone();
+    let url = &mut profile.url;
+    url.set_scheme(base.scheme())
+        .map_err(|_| anyhow!("can't change the scheme of {} to {}", url, base.scheme()))?;
+    url.set_host(base.host_str())?;
+    url.set_port(base.port())
+        .map_err(|_| anyhow!("can't change the port of {}", url))?;
+    Ok(profile)
+}
+
+/// a json or form body becomes an object, anything else is sent as it is
+fn set_text_body(profile: &mut RequestProfile, headers: &HeaderMap, text: String) {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_string());
+    match content_type.as_deref() {
+        Some("application/json") => match serde_json::from_str::<Value>(&text) {
+            Ok(body) if body.is_object() => profile.body = Some(body),
+            _ => profile.body_raw = Some(text),
+        },
+        Some("application/x-www-form-urlencoded") => {
+            let mut body = json!({});
+            for (k, v) in url::form_urlencoded::parse(text.as_bytes()) {
+                push_field(&mut body, &k, json!(v));
+            }
+            profile.body = Some(body);
+        }
+        _ => profile.body_raw = Some(text),
+    }
+}
+
+/// a repeated field becomes an array, as the form encoders write arrays as repeated fields
+fn push_field(body: &mut Value, name: &str, value: Value) {
+    match body.get_mut(name) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(prev) => *prev = json!([prev.take(), value]),
+        None => body[name] = value,
+    }
+}
+
+/// a profile name made from the method and the path, e.g. `get_todos_id`
+fn profile_name(profile: &RequestProfile) -> String {
+    let mut name = profile.method.as_str().to_lowercase();
+    let path = decode_braces(profile.url.path());
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        name.push('_');
+        name.extend(segment.chars().filter(|c| *c != '{' && *c != '}').map(|c| {
+            match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_lowercase(),
+                false => '_',
+            }
+        }));
+    }
+    name
+}
+
+/// `name`, or `name_2`, `name_3`... if it's taken
+fn unique_name(names: &mut HashSet<String>, name: &str) -> String {
+    let mut unique = name.to_string();
+    let mut n = 1;
+    while !names.insert(unique.clone()) {
+        n += 1;
+        unique = format!("{}_{}", name, n);
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebase_should_keep_path_and_params() {
+        let profile: RequestProfile = "https://api.example.com/todos/1?a=1".parse().unwrap();
+        let base = Url::parse("http://localhost:8080").unwrap();
+        let profile = rebase(&profile, &base).unwrap();
+        assert_eq!(profile.url.as_str(), "http://localhost:8080/todos/1");
+        assert_eq!(profile.params, Some(serde_json::json!({"a": 1})));
+    }
+}
@@ -0,0 +1,404 @@
+use super::{profile_name, unique_name, ImportedRequest};
+use crate::config::ValidateConfig;
+use crate::RequestProfile;
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Method, Url,
+};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// nested schemas deeper than this are left out of the generated examples
+const MAX_DEPTH: usize = 8;
+
+/// one request per operation of an OpenAPI 3 spec, in YAML or JSON
+///
+/// `server` and `server2` are an index into the `servers` of the spec or a url, the
+/// first server is used if `server` isn't given. The requests are paired with the same
+/// operations on `server2` if it's given.
+pub fn parse_openapi(
+    content: &str,
+    server: Option<&str>,
+    server2: Option<&str>,
+) -> Result<Vec<ImportedRequest>> {
+    // json is yaml as well
+    let spec: Value = serde_yaml::from_str(content).context("invalid OpenAPI spec")?;
+    let version = spec.get("openapi").and_then(|v| v.as_str()).unwrap_or("");
+    if !version.starts_with("3.") {
+        return Err(anyhow!("only OpenAPI 3 specs are supported"));
+    }
+    let base = server_url(&spec, server.unwrap_or("0"))?;
+    let base2 = server2.map(|s| server_url(&spec, s)).transpose()?;
+
+    let mut names = HashSet::new();
+    let mut requests = vec![];
+    let paths = spec.get("paths").and_then(|v| v.as_object());
+    for (path, item) in paths.into_iter().flatten() {
+        let item = resolve(&spec, item)?;
+        for method in METHODS {
+            let op = match item.get(method) {
+                Some(op) => op,
+                None => continue,
+            };
+            let profile = to_profile(&spec, &base, path, method, item, op)
+                .with_context(|| format!("failed to import {} {}", method, path))?;
+            let profile2 = match base2.as_deref() {
+                Some(base2) => {
+                    let mut profile2 = profile.clone();
+                    profile2.url = operation_url(base2, path)?;
+                    Some(profile2)
+                }
+                None => None,
+            };
+            let name = match op.get("operationId").and_then(|v| v.as_str()) {
+                Some(id) => unique_name(&mut names, id),
+                None => unique_name(&mut names, &profile_name(&profile)),
+            };
+            requests.push(ImportedRequest {
+                name,
+                profile,
+                profile2,
+                response: None,
+            });
+        }
+    }
+    Ok(requests)
+}
+
+fn to_profile(
+    spec: &Value,
+    base: &str,
+    path: &str,
+    method: &str,
+    item: &Value,
+    op: &Value,
+) -> Result<RequestProfile> {
+    let url = operation_url(base, path)?;
+    let method = Method::from_str(&method.to_uppercase())?;
+    let mut profile = RequestProfile::new(method, url, None, HeaderMap::new(), None);
+
+    let mut params = json!({});
+    let mut headers = HeaderMap::new();
+    let mut cookies = vec![];
+    for param in parameters(spec, item, op)? {
+        let name = param.get("name").and_then(|v| v.as_str()).unwrap_or("");
+        let value = match example(spec, param)? {
+            Some(value) => value,
+            // left to be given with `-e :name=value`
+            None if param.get("in").and_then(|v| v.as_str()) == Some("path") => json!(""),
+            None => continue,
+        };
+        match param.get("in").and_then(|v| v.as_str()) {
+            Some("path") => {
+                profile.path_params.insert(name.into(), text(&value));
+            }
+            Some("query") => params[name] = value,
+            Some("header") => {
+                // described by the spec elsewhere, see the parameter object
+                let name = name.to_lowercase();
+                if ["accept", "content-type", "authorization"].contains(&name.as_str()) {
+                    continue;
+                }
+                headers.insert(
+                    HeaderName::from_str(&name)?,
+                    HeaderValue::from_str(&text(&value))?,
+                );
+            }
+            Some("cookie") => cookies.push(format!("{}={}", name, text(&value))),
+            _ => {}
+        }
+    }
+    if !cookies.is_empty() {
+        headers.insert(header::COOKIE, HeaderValue::from_str(&cookies.join("; "))?);
+    }
+
+    if let Some(body) = op.get("requestBody") {
+        let body = resolve(spec, body)?;
+        if let Some((content_type, media)) = pick_media(body) {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+            match example(spec, media)? {
+                Some(Value::Object(obj)) if is_structured(content_type) => {
+                    profile.body = Some(Value::Object(obj))
+                }
+                Some(Value::String(s)) => profile.body_raw = Some(s),
+                Some(value) => profile.body_raw = Some(serde_json::to_string(&value)?),
+                None if is_structured(content_type) => {}
+                // sent as it is, left empty to be filled in by hand
+                None => profile.body_raw = Some(String::new()),
+            }
+        }
+    }
+    profile.params = Some(params);
+    profile.headers = headers;
+    profile.validate()?;
+    Ok(profile)
+}
+
+/// the parameters of the path item, overridden by those of the operation
+fn parameters<'a>(spec: &'a Value, item: &'a Value, op: &'a Value) -> Result<Vec<&'a Value>> {
+    let mut params: BTreeMap<(String, String), &Value> = BTreeMap::new();
+    for list in [item.get("parameters"), op.get("parameters")] {
+        for param in list.and_then(|v| v.as_array()).into_iter().flatten() {
+            let param = resolve(spec, param)?;
+            let key = |k: &str| {
+                param
+                    .get(k)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            params.insert((key("in"), key("name")), param);
+        }
+    }
+    Ok(params.into_values().collect())
+}
+
+/// json first, then the form types, then whatever comes first
+fn pick_media(body: &Value) -> Option<(&str, &Value)> {
+    let content = body.get("content")?.as_object()?;
+    let preferred = [
+        "application/json",
+        "application/x-www-form-urlencoded",
+        "multipart/form-data",
+    ];
+    preferred
+        .iter()
+        .find_map(|t| content.get_key_value(*t))
+        .or_else(|| content.iter().find(|(t, _)| t.ends_with("+json")))
+        .or_else(|| content.iter().next())
+        .map(|(t, v)| (t.as_str(), v))
+}
+
+/// content types xreq builds from a body object
+fn is_structured(content_type: &str) -> bool {
+    matches!(
+        content_type,
+        "application/json" | "application/x-www-form-urlencoded" | "multipart/form-data"
+    )
+}
+
+/// the example of a parameter or a media type, or one built from its schema
+fn example(spec: &Value, obj: &Value) -> Result<Option<Value>> {
+    if let Some(value) = first_example(spec, obj)? {
+        return Ok(Some(value));
+    }
+    match obj.get("schema") {
+        Some(schema) => schema_example(spec, schema, 0),
+        None => Ok(None),
+    }
+}
+
+/// `example`, or the value of the first of `examples`
+fn first_example(spec: &Value, obj: &Value) -> Result<Option<Value>> {
+    if let Some(value) = obj.get("example") {
+        return Ok(Some(value.clone()));
+    }
+    match obj.get("examples").and_then(|v| v.as_object()) {
+        Some(examples) => match examples.values().next() {
+            Some(example) => Ok(resolve(spec, example)?.get("value").cloned()),
+            None => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+/// an example built from the `example` and `default` values in a schema
+fn schema_example(spec: &Value, schema: &Value, depth: usize) -> Result<Option<Value>> {
+    if depth > MAX_DEPTH {
+        return Ok(None);
+    }
+    let schema = resolve(spec, schema)?;
+    if let Some(value) = schema.get("example").or_else(|| schema.get("default")) {
+        return Ok(Some(value.clone()));
+    }
+    // OpenAPI 3.1 schemas have an array of examples
+    if let Some(value) = schema
+        .get("examples")
+        .and_then(|v| v.as_array())
+        .and_then(|v| v.first())
+    {
+        return Ok(Some(value.clone()));
+    }
+    if let Some(all) = schema.get("allOf").and_then(|v| v.as_array()) {
+        let mut merged = Map::new();
+        for s in all {
+            if let Some(Value::Object(obj)) = schema_example(spec, s, depth + 1)? {
+                merged.extend(obj);
+            }
+        }
+        return Ok((!merged.is_empty()).then_some(Value::Object(merged)));
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(first) = schema
+            .get(key)
+            .and_then(|v| v.as_array())
+            .and_then(|v| v.first())
+        {
+            return schema_example(spec, first, depth + 1);
+        }
+    }
+    if let Some(props) = schema.get("properties").and_then(|v| v.as_object()) {
+        let mut obj = Map::new();
+        for (name, prop) in props {
+            if let Some(value) = schema_example(spec, prop, depth + 1)? {
+                obj.insert(name.clone(), value);
+            }
+        }
+        return Ok((!obj.is_empty()).then_some(Value::Object(obj)));
+    }
+    match schema.get("items") {
+        Some(items) => Ok(schema_example(spec, items, depth + 1)?.map(|v| json!([v]))),
+        None => Ok(None),
+    }
+}
+
+/// follow `$ref`s within the spec
+pub(crate) fn resolve<'a>(spec: &'a Value, mut value: &'a Value) -> Result<&'a Value> {
+    for _ in 0..32 {
+        let reference = match value.get("$ref").and_then(|v| v.as_str()) {
+            Some(reference) => reference,
+            None => return Ok(value),
+        };
+        let pointer = reference
+            .strip_prefix('#')
+            .ok_or_else(|| anyhow!("only refs within the spec are supported: {}", reference))?;
+        value = spec
+            .pointer(pointer)
+            .ok_or_else(|| anyhow!("ref not found: {}", reference))?;
+    }
+    Err(anyhow!("too many nested refs"))
+}
+
+/// a server of the spec by index, or a url, with its variables set to their defaults
+fn server_url(spec: &Value, server: &str) -> Result<String> {
+    let idx = match server.parse::<usize>() {
+        Ok(idx) => idx,
+        Err(_) => return Ok(server.trim_end_matches('/').to_string()),
+    };
+    let servers = spec.get("servers").and_then(|v| v.as_array());
+    let entry = servers.and_then(|v| v.get(idx)).ok_or_else(|| {
+        anyhow!(
+            "the spec has no server {}, use a url instead, e.g. --server https://api.example.com",
+            idx
+        )
+    })?;
+    let mut url = entry
+        .get("url")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("server {} has no url", idx))?
+        .to_string();
+    let vars = entry.get("variables").and_then(|v| v.as_object());
+    for (name, var) in vars.into_iter().flatten() {
+        let default = var.get("default").map(text).unwrap_or_default();
+        url = url.replace(&format!("{{{}}}", name), &default);
+    }
+    if Url::parse(&url).is_err() {
+        return Err(anyhow!(
+            "server {} is relative, use a url instead, e.g. --server https://api.example.com{}",
+            url,
+            url
+        ));
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+fn operation_url(base: &str, path: &str) -> Result<Url> {
+    Url::parse(&format!("{}{}", base, path))
+        .with_context(|| format!("invalid url {}{}", base, path))
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+info: {title: todos, version: "1"}
+servers:
+  - url: https://{env}.example.com/v1
+    variables:
+      env: {default: api}
+  - url: http://localhost:8080/v1
+paths:
+  /todos/{id}:
+    parameters:
+      - $ref: "#/components/parameters/TodoId"
+    get:
+      operationId: getTodo
+      parameters:
+        - {name: expand, in: query, schema: {type: string, default: user}}
+        - {name: x-tenant, in: header, example: acme}
+        - {name: page, in: query, schema: {type: integer}}
+    put:
+      requestBody:
+        content:
+          application/json:
+            schema: {$ref: "#/components/schemas/Todo"}
+components:
+  parameters:
+    TodoId: {name: id, in: path, required: true, example: 1}
+  schemas:
+    Todo:
+      type: object
+      properties:
+        title: {type: string, example: hello}
+        done: {type: boolean, default: false}
+        tags: {type: array, items: {type: string, example: work}}
+        note: {type: string}
+"##;
+
+    #[test]
+    fn parse_openapi_should_work() {
+        let requests = parse_openapi(SPEC, None, Some("1")).unwrap();
+        let names: Vec<_> = requests.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["getTodo", "put_v1_todos_id"]);
+
+        let get = &requests[0].profile;
+        assert_eq!(get.method, Method::GET);
+        assert_eq!(
+            get.url.as_str(),
+            "https://api.example.com/v1/todos/%7Bid%7D"
+        );
+        assert_eq!(get.path_params["id"], "1");
+        assert_eq!(get.params, Some(json!({"expand": "user"})));
+        assert_eq!(get.headers["x-tenant"], "acme");
+        assert_eq!(
+            get.get_url(&Default::default()).unwrap(),
+            "https://api.example.com/v1/todos/1?expand=user"
+        );
+        let yaml = serde_yaml::to_string(get).unwrap();
+        assert!(yaml.contains("url: https://api.example.com/v1/todos/{id}\n"));
+        let get2 = requests[0].profile2.as_ref().unwrap();
+        assert_eq!(get2.url.as_str(), "http://localhost:8080/v1/todos/%7Bid%7D");
+
+        let put = &requests[1].profile;
+        assert_eq!(put.headers["content-type"], "application/json");
+        assert_eq!(
+            put.body,
+            Some(json!({"title": "hello", "done": false, "tags": ["work"]}))
+        );
+    }
+
+    #[test]
+    fn parse_openapi_with_relative_server_should_need_url() {
+        let spec = "openapi: 3.0.0\nservers: [{url: /v1}]\npaths: {}\n";
+        assert!(parse_openapi(spec, None, None).is_err());
+        assert!(parse_openapi(spec, Some("http://localhost/v1"), None).is_ok());
+        assert!(parse_openapi("swagger: '2.0'\npaths: {}\n", None, None).is_err());
+    }
+}
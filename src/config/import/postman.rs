@@ -0,0 +1,668 @@
+use super::{push_field, set_text_body, unique_name, Imported, ImportedRequest};
+use crate::config::{chain::render_vars, query_params, ValidateConfig};
+use crate::{Auth, RequestProfile, Secret, Vars};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Method, Url,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+#[derive(Debug, Deserialize)]
+struct Collection {
+    #[serde(default)]
+    item: Vec<Item>,
+    #[serde(default)]
+    variable: Vec<KeyValue>,
+    auth: Option<PostmanAuth>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+/// a folder has items, a request has a request
+#[derive(Debug, Deserialize)]
+struct Item {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    item: Vec<Item>,
+    request: Option<PostmanRequest>,
+    auth: Option<PostmanAuth>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PostmanRequest {
+    Url(String),
+    Full(Box<FullRequest>),
+}
+
+#[derive(Debug, Deserialize)]
+struct FullRequest {
+    #[serde(default = "default_method")]
+    method: String,
+    url: Option<PostmanUrl>,
+    #[serde(default)]
+    header: Vec<KeyValue>,
+    body: Option<PostmanBody>,
+    auth: Option<PostmanAuth>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PostmanUrl {
+    Raw(String),
+    Full {
+        #[serde(default)]
+        raw: String,
+        #[serde(default)]
+        variable: Vec<KeyValue>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct PostmanBody {
+    #[serde(default)]
+    mode: String,
+    raw: Option<String>,
+    #[serde(default)]
+    urlencoded: Vec<KeyValue>,
+    #[serde(default)]
+    formdata: Vec<FormParam>,
+    file: Option<FileBody>,
+    graphql: Option<GraphQl>,
+    options: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormParam {
+    key: String,
+    value: Option<String>,
+    #[serde(rename = "type", default)]
+    kind: String,
+    src: Option<Value>,
+    #[serde(rename = "contentType")]
+    content_type: Option<String>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileBody {
+    src: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQl {
+    #[serde(default)]
+    query: String,
+    variables: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PostmanAuth {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    params: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(default)]
+    listen: String,
+    script: Option<Script>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Script {
+    #[serde(default)]
+    exec: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Environment {
+    #[serde(default)]
+    values: Vec<KeyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyValue {
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    enabled: Option<bool>,
+    /// `secret` for the values Postman masks
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+impl KeyValue {
+    fn is_on(&self) -> bool {
+        !self.disabled && self.enabled.unwrap_or(true)
+    }
+
+    fn text(&self) -> String {
+        match &self.value {
+            Value::String(s) => s.clone(),
+            Value::Null => String::new(),
+            v => v.to_string(),
+        }
+    }
+}
+
+/// the requests of a Postman v2.1 collection, with the variables of the collection and the
+/// environment as `vars`
+///
+/// `{{name}}` references are kept for xreq to fill, except in the scheme and host of a
+/// url which have to be known to load the config. Scripts, dynamic variables like
+/// `{{$guid}}` and the auth types xreq doesn't have are reported as warnings.
+pub fn parse_postman(collection: &str, environment: Option<&str>) -> Result<Imported> {
+    let collection: Collection =
+        serde_json::from_str(collection).context("invalid Postman collection")?;
+    let environment: Option<Environment> = environment
+        .map(|e| serde_json::from_str(e).context("invalid Postman environment"))
+        .transpose()?;
+    // the environment wins over the collection
+    let mut values = BTreeMap::new();
+    let env_values = environment.iter().flat_map(|e| e.values.iter());
+    for var in collection.variable.iter().chain(env_values) {
+        if var.is_on() {
+            values.insert(var.key.clone(), (var.text(), var.kind == "secret"));
+        }
+    }
+    // secrets are only used to fill the host of a url, they're not printed
+    let mut vars = BTreeMap::new();
+    let mut warnings = vec![];
+    for (name, (value, secret)) in values.iter() {
+        match secret {
+            true => warnings.push(format!(
+                "the secret {{{{{}}}}} is left out of the vars, set it in the config",
+                name
+            )),
+            false => {
+                vars.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    let mut importer = Importer {
+        vars: values.into_iter().map(|(k, (v, _))| (k, v)).collect(),
+        names: HashSet::new(),
+        imported: Imported {
+            vars,
+            warnings,
+            ..Default::default()
+        },
+    };
+    importer.check_scripts("the collection", &collection.event);
+    importer.import_items(&collection.item, "", collection.auth.as_ref());
+    Ok(importer.imported)
+}
+
+struct Importer {
+    vars: Vars,
+    names: HashSet<String>,
+    imported: Imported,
+}
+
+impl Importer {
+    fn import_items(&mut self, items: &[Item], prefix: &str, auth: Option<&PostmanAuth>) {
+        for item in items {
+            let name = match prefix.is_empty() {
+                true => slug(&item.name),
+                false => format!("{}_{}", prefix, slug(&item.name)),
+            };
+            // `inherit` takes the auth of the parent, as a missing auth does
+            let auth = match item.auth.as_ref() {
+                Some(a) if a.kind != "inherit" => Some(a),
+                _ => auth,
+            };
+            self.check_scripts(&format!("`{}`", item.name), &item.event);
+            match item.request.as_ref() {
+                Some(req) => match self.build_profile(req, auth) {
+                    Ok(profile) => {
+                        self.check_vars(&item.name, &profile);
+                        let name = unique_name(&mut self.names, &name);
+                        self.imported.requests.push(ImportedRequest {
+                            name,
+                            profile,
+                            profile2: None,
+                            response: None,
+                        });
+                    }
+                    Err(e) => self
+                        .imported
+                        .warnings
+                        .push(format!("`{}` is skipped: {:#}", item.name, e)),
+                },
+                None => self.import_items(&item.item, &name, auth),
+            }
+        }
+    }
+
+    fn build_profile(
+        &mut self,
+        req: &PostmanRequest,
+        auth: Option<&PostmanAuth>,
+    ) -> Result<RequestProfile> {
+        let req = match req {
+            // a url alone is a GET request
+            PostmanRequest::Url(url) => &FullRequest {
+                method: default_method(),
+                url: Some(PostmanUrl::Raw(url.clone())),
+                header: vec![],
+                body: None,
+                auth: None,
+            },
+            PostmanRequest::Full(req) => req,
+        };
+        let (raw, variables) = match req.url.as_ref() {
+            Some(PostmanUrl::Raw(raw)) => (raw.as_str(), &[][..]),
+            Some(PostmanUrl::Full { raw, variable }) => (raw.as_str(), variable.as_slice()),
+            None => return Err(anyhow!("no url")),
+        };
+        let (url, params, path_params) = self.parse_url(raw, variables)?;
+        let method = Method::from_str(&req.method.to_uppercase())?;
+        let mut profile = RequestProfile::new(method, url, Some(params), HeaderMap::new(), None);
+        profile.path_params = path_params;
+
+        let mut headers = HeaderMap::new();
+        for h in req.header.iter().filter(|h| h.is_on()) {
+            headers.append(
+                HeaderName::from_str(&h.key)?,
+                HeaderValue::from_str(&h.text())?,
+            );
+        }
+        if let Some(body) = req.body.as_ref() {
+            self.set_body(&mut profile, &mut headers, body)?;
+        }
+        let auth = match req.auth.as_ref() {
+            Some(a) if a.kind != "inherit" => Some(a),
+            _ => auth,
+        };
+        if let Some(auth) = auth {
+            self.set_auth(&mut profile, &mut headers, auth)?;
+        }
+        profile.headers = headers;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// the url, its query params and its `:name` path variables as `{name}` placeholders
+    fn parse_url(
+        &self,
+        raw: &str,
+        variables: &[KeyValue],
+    ) -> Result<(Url, Value, BTreeMap<String, String>)> {
+        let raw = match raw.contains("://") || raw.starts_with("{{") {
+            true => raw.to_string(),
+            false => format!("http://{}", raw),
+        };
+        // the scheme and host have to be known, the path and query could keep variables
+        let start = raw.find("://").map_or(0, |idx| idx + 3);
+        let origin_end = raw[start..]
+            .find(['/', '?'])
+            .map_or(raw.len(), |idx| start + idx);
+        let origin = render_vars(&raw[..origin_end], &self.vars);
+        let rest = &raw[origin_end..];
+        if origin.contains("{{") {
+            return Err(anyhow!("unknown variable in {}", origin));
+        }
+        let origin = match origin.contains("://") {
+            true => origin,
+            false => format!("http://{}", origin),
+        };
+
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+        let mut path_params = BTreeMap::new();
+        let path = path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) if !name.is_empty() => {
+                    // left empty to be given with `-e :name=value`
+                    let value = variables.iter().find(|v| v.key == name);
+                    let value = value.map(|v| v.text()).unwrap_or_default();
+                    path_params.insert(name.to_string(), value);
+                    format!("{{{}}}", name)
+                }
+                _ => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut url = Url::parse(&format!("{}{}", origin, path))
+            .with_context(|| format!("invalid url {}", raw))?;
+        url.set_query(query);
+        let params = query_params(&url);
+        url.set_query(None);
+        Ok((url, params, path_params))
+    }
+
+    fn set_body(
+        &mut self,
+        profile: &mut RequestProfile,
+        headers: &mut HeaderMap,
+        body: &PostmanBody,
+    ) -> Result<()> {
+        let mut set_type = |content_type: &'static str| {
+            if !headers.contains_key(header::CONTENT_TYPE) {
+                headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+            }
+        };
+        match body.mode.as_str() {
+            "raw" => {
+                let language = body
+                    .options
+                    .as_ref()
+                    .and_then(|o| o.pointer("/raw/language"))
+                    .and_then(|v| v.as_str());
+                match language {
+                    Some("json") => set_type("application/json"),
+                    Some("xml") => set_type("application/xml"),
+                    Some("html") => set_type("text/html"),
+                    _ => set_type("text/plain"),
+                }
+                let raw = body.raw.clone().unwrap_or_default();
+                set_text_body(profile, headers, raw);
+            }
+            "urlencoded" => {
+                set_type("application/x-www-form-urlencoded");
+                let mut fields = json!({});
+                for f in body.urlencoded.iter().filter(|f| f.is_on()) {
+                    push_field(&mut fields, &f.key, json!(f.text()));
+                }
+                profile.body = Some(fields);
+            }
+            "formdata" => {
+                set_type("multipart/form-data");
+                let mut fields = json!({});
+                for f in body.formdata.iter().filter(|f| !f.disabled) {
+                    let value = match f.kind.as_str() {
+                        "file" => {
+                            let src = match f.src.as_ref() {
+                                Some(Value::String(src)) => src.clone(),
+                                Some(Value::Array(srcs)) if srcs.len() == 1 => {
+                                    srcs[0].as_str().unwrap_or_default().to_string()
+                                }
+                                _ => return Err(anyhow!("form field {} needs one file", f.key)),
+                            };
+                            let mut part = json!({ "file": src });
+                            if let Some(content_type) = f.content_type.as_ref() {
+                                part["content_type"] = json!(content_type);
+                            }
+                            part
+                        }
+                        _ => json!(f.value.clone().unwrap_or_default()),
+                    };
+                    push_field(&mut fields, &f.key, value);
+                }
+                profile.body = Some(fields);
+            }
+            "file" => {
+                set_type("application/octet-stream");
+                let src = body.file.as_ref().and_then(|f| f.src.clone());
+                profile.body_file =
+                    Some(src.ok_or_else(|| anyhow!("no file for the body"))?.into());
+            }
+            "graphql" => {
+                set_type("application/json");
+                let graphql = body.graphql.as_ref();
+                let query = graphql.map(|g| g.query.clone()).unwrap_or_default();
+                let variables = match graphql.and_then(|g| g.variables.as_deref()) {
+                    Some(v) if !v.trim().is_empty() => {
+                        serde_json::from_str(v).context("graphql variables must be json")?
+                    }
+                    _ => json!({}),
+                };
+                profile.body = Some(json!({ "query": query, "variables": variables }));
+            }
+            "" | "none" => {}
+            mode => return Err(anyhow!("unsupported body mode {}", mode)),
+        }
+        Ok(())
+    }
+
+    /// `{{name}}` references in the auth values are kept, xreq fills them when sending
+    fn set_auth(
+        &mut self,
+        profile: &mut RequestProfile,
+        headers: &mut HeaderMap,
+        auth: &PostmanAuth,
+    ) -> Result<()> {
+        let get = |key: &str| -> Option<String> {
+            let value = match auth.params.get(&auth.kind)? {
+                // v2.1 has a list of key/value pairs, v2.0 an object
+                Value::Array(pairs) => pairs
+                    .iter()
+                    .find(|p| p.get("key").and_then(|k| k.as_str()) == Some(key))?
+                    .get("value")?
+                    .clone(),
+                Value::Object(obj) => obj.get(key)?.clone(),
+                _ => return None,
+            };
+            let value = match value {
+                Value::String(s) => s,
+                v => v.to_string(),
+            };
+            Some(value)
+        };
+        let secret = |value: String| Secret::Value(value);
+        profile.auth = match auth.kind.as_str() {
+            "noauth" => None,
+            "bearer" => Some(Auth::Bearer {
+                token: secret(get("token").unwrap_or_default()),
+            }),
+            "basic" => Some(Auth::Basic {
+                username: get("username").unwrap_or_default(),
+                password: get("password").map(secret),
+            }),
+            "digest" => Some(Auth::Digest {
+                username: get("username").unwrap_or_default(),
+                password: secret(get("password").unwrap_or_default()),
+            }),
+            "apikey" => {
+                let key = get("key").unwrap_or_default();
+                let value = get("value").unwrap_or_default();
+                match get("in").as_deref() {
+                    Some("query") => {
+                        if let Some(params) = profile.params.as_mut() {
+                            params[key] = json!(value);
+                        }
+                    }
+                    _ => {
+                        headers.insert(HeaderName::from_str(&key)?, HeaderValue::from_str(&value)?);
+                    }
+                }
+                None
+            }
+            kind => return Err(anyhow!("unsupported auth type {}", kind)),
+        };
+        Ok(())
+    }
+
+    fn check_scripts(&mut self, owner: &str, events: &[Event]) {
+        for event in events {
+            let exec = event.script.as_ref().map(|s| &s.exec);
+            let empty = match exec {
+                Some(Value::Array(lines)) => lines
+                    .iter()
+                    .all(|l| l.as_str().is_none_or(|l| l.trim().is_empty())),
+                Some(Value::String(s)) => s.trim().is_empty(),
+                _ => true,
+            };
+            if !empty {
+                self.imported.warnings.push(format!(
+                    "the {} script of {} is not run",
+                    event.listen, owner
+                ));
+            }
+        }
+    }
+
+    /// report the variables nothing would fill in
+    fn check_vars(&mut self, name: &str, profile: &RequestProfile) {
+        let text = serde_yaml::to_string(profile).unwrap_or_default();
+        let mut unknown = vec![];
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => break,
+            };
+            let var = &rest[start + 2..end];
+            if !self.vars.contains_key(var) && !unknown.contains(&var) {
+                unknown.push(var);
+            }
+            rest = &rest[end + 2..];
+        }
+        for var in unknown {
+            let warning = match var.starts_with('$') {
+                true => format!("`{}` uses the dynamic variable {{{{{}}}}}", name, var),
+                false => format!("`{}` uses the undefined variable {{{{{}}}}}", name, var),
+            };
+            self.imported.warnings.push(warning);
+        }
+    }
+}
+
+/// `Get user (v2)` becomes `get_user_v2`
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        match c.is_ascii_alphanumeric() {
+            true => slug.push(c.to_ascii_lowercase()),
+            false if !slug.ends_with('_') => slug.push('_'),
+            false => {}
+        }
+    }
+    slug.trim_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = r#"{
+        "info": {"name": "todos", "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"},
+        "variable": [{"key": "baseUrl", "value": "https://api.example.com"}, {"key": "page", "value": "1"}],
+        "auth": {"type": "bearer", "bearer": [{"key": "token", "value": "{{token}}", "type": "string"}]},
+        "item": [
+            {
+                "name": "Todos",
+                "item": [
+                    {
+                        "name": "Get todo",
+                        "request": {
+                            "method": "GET",
+                            "header": [{"key": "x-tenant", "value": "{{tenant}}"}, {"key": "x-off", "value": "1", "disabled": true}],
+                            "url": {
+                                "raw": "{{baseUrl}}/todos/:id?page={{page}}",
+                                "variable": [{"key": "id", "value": "42"}]
+                            }
+                        }
+                    },
+                    {
+                        "name": "Create todo",
+                        "event": [{"listen": "test", "script": {"exec": ["pm.test('ok', () => {})"]}}],
+                        "request": {
+                            "method": "POST",
+                            "auth": {"type": "noauth"},
+                            "url": "{{baseUrl}}/todos",
+                            "body": {"mode": "raw", "raw": "{\"title\": \"{{$randomWord}}\"}", "options": {"raw": {"language": "json"}}}
+                        }
+                    },
+                    {
+                        "name": "List todos",
+                        "request": "{{baseUrl}}/todos"
+                    },
+                    {
+                        "name": "Sign",
+                        "request": {"method": "GET", "auth": {"type": "awsv4"}, "url": "{{baseUrl}}/sign"}
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    const ENVIRONMENT: &str = r#"{
+        "name": "staging",
+        "values": [
+            {"key": "baseUrl", "value": "https://staging.example.com", "enabled": true},
+            {"key": "token", "value": "t0ken", "type": "secret", "enabled": true},
+            {"key": "tenant", "value": "acme", "enabled": false}
+        ]
+    }"#;
+
+    #[test]
+    fn parse_postman_should_work() {
+        let imported = parse_postman(COLLECTION, Some(ENVIRONMENT)).unwrap();
+        let names: Vec<_> = imported.requests.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["todos_get_todo", "todos_create_todo", "todos_list_todos"]
+        );
+        assert_eq!(imported.vars["baseUrl"], "https://staging.example.com");
+        assert_eq!(imported.vars["page"], "1");
+        assert!(!imported.vars.contains_key("token"));
+
+        let get = &imported.requests[0].profile;
+        assert_eq!(
+            get.url.as_str(),
+            "https://staging.example.com/todos/%7Bid%7D"
+        );
+        assert_eq!(get.path_params["id"], "42");
+        assert_eq!(get.params, Some(json!({"page": "{{page}}"})));
+        assert_eq!(get.headers["x-tenant"], "{{tenant}}");
+        assert!(!get.headers.contains_key("x-off"));
+        assert_eq!(
+            get.auth,
+            Some(Auth::Bearer {
+                token: Secret::Value("{{token}}".into())
+            })
+        );
+
+        let create = &imported.requests[1].profile;
+        assert_eq!(create.auth, None);
+        assert_eq!(create.body, Some(json!({"title": "{{$randomWord}}"})));
+
+        let list = &imported.requests[2].profile;
+        assert_eq!(list.url.as_str(), "https://staging.example.com/todos");
+        assert_eq!(list.auth, get.auth);
+
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "the secret {{token}} is left out of the vars, set it in the config",
+                "`Get todo` uses the undefined variable {{tenant}}",
+                "the test script of `Create todo` is not run",
+                "`Create todo` uses the dynamic variable {{$randomWord}}",
+                "`Sign` is skipped: unsupported auth type awsv4",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_postman_without_environment_should_use_collection_vars() {
+        let imported = parse_postman(COLLECTION, None).unwrap();
+        assert_eq!(imported.requests.len(), 3);
+        assert_eq!(
+            imported.requests[0].profile.url.as_str(),
+            "https://api.example.com/todos/%7Bid%7D"
+        );
+    }
+}
@@ -1,17 +1,58 @@
+mod auth;
+mod chain;
+mod compression;
+mod contract;
+mod cookie_jar;
+mod export;
+mod form;
+mod import;
+mod path;
+mod query;
+mod redirect;
+mod retry;
+mod rewrite;
+mod schema;
+mod sign;
+mod transport;
+#[cfg(unix)]
+mod unix;
 mod xdiff;
 mod xreq;
 pub use self::xdiff::*;
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, Context, Error, Result};
 use async_trait::async_trait;
+use auth::REDACTED;
+pub use auth::{Auth, OAuth2, Secret};
+pub use chain::{lookup_json, Capture, SetupStep, Vars};
+pub use compression::{Compression, Encoding, EncodingReport};
+pub use contract::Contract;
+use contract::{ContractCache, LoadedContract};
+pub use cookie_jar::CookieJar;
+pub use import::{
+    import_requests, parse_curl, parse_har, parse_openapi, parse_postman, rebase, HarFilter,
+    Imported, ImportedRequest,
+};
+pub use query::QueryStyle;
+pub use redirect::{Redirect, RedirectHop, RedirectMode};
 use reqwest::{
     header::{self, HeaderMap, HeaderName, HeaderValue},
-    Client, Method, Response, Url,
+    Client, ClientBuilder, Method, Request, Response, StatusCode, Url,
 };
+pub use retry::{RetryPolicy, Timeouts};
+pub use rewrite::BaseRewrite;
+use rewrite::UrlRewriter;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::json;
+pub use sign::{AwsSigV4, HmacAlgorithm, HmacSigning, RequestSigner, SignatureEncoding, Signing};
+use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use tokio::fs;
+pub use transport::{HttpVersion, Transport};
+use url::Position;
 pub use xreq::RequestConfig;
 
 use crate::ExtraArgs;
@@ -29,10 +70,14 @@ where
 
     /// load config from yaml string
     fn from_yaml(content: &str) -> Result<Self> {
-        let config: Self = serde_yaml::from_str(content)?;
+        let mut config: Self = serde_yaml::from_str(content)?;
+        config.apply_defaults();
         config.validate()?;
         Ok(config)
     }
+
+    /// fill the config-wide `defaults` into the profiles
+    fn apply_defaults(&mut self) {}
 }
 pub trait ValidateConfig {
     fn validate(&self) -> Result<()>;
@@ -42,9 +87,18 @@ pub trait ValidateConfig {
 pub struct RequestProfile {
     #[serde(with = "http_serde::method", default)]
     pub method: Method,
+    /// could have `{name}` placeholders in its path, filled from `path_params` or `-e :name=value`
+    ///
+    /// a placeholder without a value is only an error once the request is sent.
+    #[serde(serialize_with = "serialize_url")]
     pub url: Url,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub path_params: BTreeMap<String, String>,
     #[serde(skip_serializing_if = "empty_json_value", default)]
     pub params: Option<serde_json::Value>,
+    /// how arrays in `params` are written into the query string, `repeat` by default
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query_style: Option<QueryStyle>,
     #[serde(
         skip_serializing_if = "HeaderMap::is_empty",
         with = "http_serde::header_map",
@@ -53,10 +107,82 @@ pub struct RequestProfile {
     pub headers: HeaderMap,
     #[serde(skip_serializing_if = "empty_json_value", default)]
     pub body: Option<serde_json::Value>,
+    /// a body sent verbatim, needs an explicit `content-type` header
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub body_raw: Option<String>,
+    /// a file whose content is sent verbatim, needs an explicit `content-type` header
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub body_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub auth: Option<Auth>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sign: Option<Signing>,
+    /// file to keep the cookies in between runs
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cookie_jar: Option<PathBuf>,
+    /// values for `{{name}}`, the setup steps could capture new ones over them
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub vars: BTreeMap<String, String>,
+    /// requests to run first, their captured values could be used as `{{name}}`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub setup: Vec<SetupStep>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timeout: Option<Timeouts>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub transport: Option<Transport>,
+    /// connect to these addresses instead of resolving the host, like `curl --resolve`
+    ///
+    /// keys are `host` or `host:port`, the url, SNI and `Host` header stay untouched.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub resolve: BTreeMap<String, IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub redirect: Option<Redirect>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub compression: Option<Compression>,
+    /// send the request over this unix socket, the url only gives the path and `Host`
+    ///
+    /// a url like `unix:///path/to.sock:/http/path` sets it as well.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub socket: Option<PathBuf>,
+}
+
+/// Settings shared by all the profiles in a config, unless a profile sets its own
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ProfileDefaults {
+    /// merged into the `vars` of each profile, which win on the same name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub query_style: Option<QueryStyle>,
+    /// one jar for the profiles without their own, cookies are kept by domain so the
+    /// hosts don't mix
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cookie_jar: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timeout: Option<Timeouts>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub transport: Option<Transport>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub resolve: BTreeMap<String, IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub redirect: Option<Redirect>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub compression: Option<Compression>,
 }
 
 #[derive(Debug)]
-pub struct ResponseExt(Response);
+pub struct ResponseExt {
+    res: Response,
+    /// the url the request was sent to, before any redirect
+    url: Url,
+    attempts: u32,
+    redirects: Vec<RedirectHop>,
+    encoding: Option<EncodingReport>,
+}
 
 impl RequestProfile {
     pub fn new(
@@ -69,38 +195,270 @@ impl RequestProfile {
         Self {
             method,
             url,
+            path_params: BTreeMap::new(),
             params,
+            query_style: None,
             headers,
             body,
+            body_raw: None,
+            body_file: None,
+            auth: None,
+            sign: None,
+            cookie_jar: None,
+            vars: BTreeMap::new(),
+            setup: vec![],
+            timeout: None,
+            retry: None,
+            transport: None,
+            resolve: BTreeMap::new(),
+            redirect: None,
+            compression: None,
+            socket: None,
+        }
+    }
+    pub fn apply_defaults(&mut self, defaults: &ProfileDefaults) {
+        for (k, v) in defaults.vars.iter() {
+            self.vars.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        if self.query_style.is_none() {
+            self.query_style = defaults.query_style;
+        }
+        if self.cookie_jar.is_none() {
+            self.cookie_jar = defaults.cookie_jar.clone();
+        }
+        if self.timeout.is_none() {
+            self.timeout = defaults.timeout;
+        }
+        if self.retry.is_none() {
+            self.retry = defaults.retry.clone();
+        }
+        merge_transport(&mut self.transport, defaults.transport.as_ref());
+        if self.resolve.is_empty() {
+            self.resolve = defaults.resolve.clone();
+        }
+        if self.redirect.is_none() {
+            self.redirect = defaults.redirect;
+        }
+        if self.compression.is_none() {
+            self.compression = defaults.compression.clone();
+        }
+        for step in self.setup.iter_mut() {
+            step.request.apply_defaults(defaults);
         }
     }
     pub async fn send(&self, args: &ExtraArgs) -> Result<ResponseExt> {
-        // let req = Client::new().request(self.method.clone(), self.url.clone());
-        let (headers, query, body) = self.generate(args)?;
-        let client = Client::new();
-        // let url = self.url.clone().set_query(query);
-        // println!("{:?}", args);
-        // println!("{:?}", self.url.clone());
-        let req = client
-            .request(self.method.clone(), self.url.clone())
-            .query(&query)
+        let vars = self.run_setup().await?;
+        self.render(&vars)?.execute(args).await
+    }
+    /// run the setup steps in order, each one could use what the previous ones captured
+    pub async fn run_setup(&self) -> Result<Vars> {
+        let mut vars: Vars = self.vars.clone().into_iter().collect();
+        for (idx, step) in self.setup.iter().enumerate() {
+            let mut req = step.request.render(&vars)?;
+            // steps share the session and network of the profile unless they have their own
+            if req.cookie_jar.is_none() {
+                req.cookie_jar = self.cookie_jar.clone();
+            }
+            merge_transport(&mut req.transport, self.transport.as_ref());
+            if req.resolve.is_empty() {
+                req.resolve = self.resolve.clone();
+            }
+            let res = req
+                .execute(&ExtraArgs::default())
+                .await
+                .with_context(|| format!("setup step {} failed", idx + 1))?;
+            vars.extend(Capture::extract(res, &step.capture).await?);
+        }
+        Ok(vars)
+    }
+    /// send the request itself, without running the setup steps
+    async fn execute(&self, args: &ExtraArgs) -> Result<ResponseExt> {
+        let (mut headers, query, body) = self.generate(args)?;
+        let url = self.request_url(args, &query)?;
+        let compression = self.compression.clone().unwrap_or_default();
+        compression.apply(&mut headers)?;
+        if let Some(auth) = self.auth.as_ref() {
+            auth.apply(&mut headers, &self.client_builder().await?.build()?)
+                .await?;
+        }
+        let jar = self.load_cookie_jar().await?.map(Arc::new);
+        let redirects = Arc::new(Mutex::new(vec![]));
+        let client = self.build_client(jar.clone(), redirects.clone()).await?;
+        let (mut res, mut attempts) = self
+            .execute_with_retry(&client, jar.as_deref(), || {
+                redirects.lock().unwrap().clear();
+                self.build_request(&client, url.clone(), headers.clone(), body.clone())
+            })
+            .await?;
+
+        // schemes like digest need to see the server's challenge before answering
+        if let (Some(auth), StatusCode::UNAUTHORIZED) = (self.auth.as_ref(), res.status()) {
+            // the url as requested, responses from a socket don't know theirs
+            let uri = url[Position::BeforePath..].to_string();
+            let answer = match res.headers().get(header::WWW_AUTHENTICATE) {
+                Some(challenge) => auth.respond(challenge, &self.method, &uri)?,
+                None => None,
+            };
+            if let Some(value) = answer {
+                headers.insert(header::AUTHORIZATION, value);
+                let (answered, retries) = self
+                    .execute_with_retry(&client, jar.as_deref(), || {
+                        redirects.lock().unwrap().clear();
+                        self.build_request(&client, url.clone(), headers.clone(), body.clone())
+                    })
+                    .await?;
+                res = answered;
+                attempts += retries;
+            }
+        }
+        if let Some(jar) = jar {
+            jar.save().await?;
+        }
+        let (res, encoding) = compression.decode(res).await?;
+        let redirects = redirects.lock().unwrap().clone();
+        Ok(ResponseExt {
+            res,
+            url,
+            attempts,
+            redirects,
+            encoding,
+        })
+    }
+    /// a client with all the connection settings of the profile
+    async fn build_client(
+        &self,
+        jar: Option<Arc<CookieJar>>,
+        redirects: redirect::RedirectChain,
+    ) -> Result<Client> {
+        let policy = self.redirect.unwrap_or_default().policy(redirects);
+        let mut builder = self.client_builder().await?.redirect(policy);
+        if let Some(jar) = jar {
+            builder = builder.cookie_provider(jar);
+        }
+        Ok(builder.build()?)
+    }
+    /// the timeouts, transport and resolve overrides of the profile, which every request
+    /// it makes goes through, like fetching an oauth2 token
+    async fn client_builder(&self) -> Result<ClientBuilder> {
+        let mut builder = Client::builder();
+        if let Some(timeout) = self.timeout.as_ref() {
+            if let Some(connect) = timeout.connect {
+                builder = builder.connect_timeout(connect);
+            }
+            if let Some(total) = timeout.total {
+                builder = builder.timeout(total);
+            }
+        }
+        if let Some(transport) = self.transport.as_ref() {
+            builder = transport.apply(builder).await?;
+        }
+        let port = self.url.port_or_known_default().unwrap_or_default();
+        for (target, ip) in self.resolve.iter() {
+            let (host, target_port) = parse_resolve_target(target)?;
+            if target_port.is_none_or(|p| p == port) {
+                builder = builder.resolve(host, SocketAddr::new(*ip, port));
+            }
+        }
+        Ok(builder)
+    }
+    /// send the request built by `build`, retrying as the retry policy says
+    async fn execute_with_retry<F>(
+        &self,
+        client: &Client,
+        jar: Option<&CookieJar>,
+        build: F,
+    ) -> Result<(Response, u32)>
+    where
+        F: Fn() -> Result<Request>,
+    {
+        let policy = self.retry.clone().unwrap_or_default();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let retry = attempts <= policy.count;
+            let res = match self.socket.as_ref() {
+                #[cfg(unix)]
+                Some(socket) => {
+                    let http_version = self.transport.as_ref().and_then(|t| t.http_version);
+                    unix::execute(socket, build()?, jar, self.timeout, http_version).await
+                }
+                #[cfg(not(unix))]
+                Some(_) => Err(anyhow!("unix sockets are only supported on unix")),
+                None => client.execute(build()?).await.map_err(Error::from),
+            };
+            match res {
+                Ok(res) if retry && policy.should_retry_status(&self.method, res.status()) => {}
+                Err(e) if retry && policy.should_retry_error(&e) => {}
+                Ok(res) => return Ok((res, attempts)),
+                Err(e) => {
+                    return Err(e.context(format!("request failed after {} attempt(s)", attempts)))
+                }
+            }
+            tokio::time::sleep(policy.delay(attempts - 1)).await;
+        }
+    }
+    pub async fn load_cookie_jar(&self) -> Result<Option<CookieJar>> {
+        match self.cookie_jar.as_ref() {
+            Some(path) => Ok(Some(CookieJar::load(path).await?)),
+            None => Ok(None),
+        }
+    }
+    /// build the final request, signing is the last step as it covers everything else
+    fn build_request(
+        &self,
+        client: &Client,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Request> {
+        let mut req = client
+            .request(self.method.clone(), url)
             .headers(headers)
             .body(body)
             .build()?;
-        let res = client.execute(req).await?;
-        Ok(ResponseExt(res))
+        if let Some(signer) = self.sign.as_ref() {
+            signer.sign(&mut req)?;
+        }
+        Ok(req)
     }
     pub fn get_url(&self, args: &ExtraArgs) -> Result<String> {
-        let mut url = self.url.clone();
         let (_, params, _) = self.generate(args)?;
-
-        if !params.as_object().unwrap().is_empty() {
-            let query = serde_qs::to_string(&params)?;
-            url.set_query(Some(&query));
+        Ok(self.request_url(args, &params)?.to_string())
+    }
+    /// the url with the path params filled in and the query params appended, exactly as
+    /// it's requested
+    fn request_url(&self, args: &ExtraArgs, query: &serde_json::Value) -> Result<Url> {
+        let mut url = self.url.clone();
+        let path = decode_braces(url.path());
+        if !path::path_param_names(&path)?.is_empty() {
+            let mut params = self.path_params.clone();
+            params.extend(args.path.iter().cloned());
+            url.set_path(&path::fill_path_params(&path, &params)?);
+        }
+        let pairs = query::query_pairs(query, self.query_style.unwrap_or_default())?;
+        if !pairs.is_empty() {
+            url.query_pairs_mut().extend_pairs(pairs);
         }
-        Ok(url.to_string())
+        Ok(url)
     }
-    pub fn generate(&self, args: &ExtraArgs) -> Result<(HeaderMap, serde_json::Value, String)> {
+    /// the body to send verbatim: stdin first, then `body_raw`, then `body_file`
+    fn raw_body(&self, args: &ExtraArgs) -> Result<Option<Vec<u8>>> {
+        if let Some(raw) = args.body_raw.as_ref() {
+            return Ok(Some(raw.clone()));
+        }
+        if let Some(raw) = self.body_raw.as_ref() {
+            return Ok(Some(raw.clone().into_bytes()));
+        }
+        match self.body_file.as_ref() {
+            Some(path) => {
+                let content = std::fs::read(path)
+                    .with_context(|| format!("failed to read body file {}", path.display()))?;
+                Ok(Some(content))
+            }
+            None => Ok(None),
+        }
+    }
+    pub fn generate(&self, args: &ExtraArgs) -> Result<(HeaderMap, serde_json::Value, Vec<u8>)> {
         let mut headers = self.headers.clone();
         let mut query = self.params.clone().unwrap_or_else(|| json!({}));
         let mut body = self.body.clone().unwrap_or_else(|| json!({}));
@@ -108,6 +466,21 @@ impl RequestProfile {
         for (k, v) in &args.headers {
             headers.insert(HeaderName::from_str(k)?, HeaderValue::from_str(v)?);
         }
+
+        for (k, v) in &args.query {
+            query[k] = parse_value(v);
+        }
+
+        if let Some(raw) = self.raw_body(args)? {
+            if !args.body.is_empty() {
+                return Err(anyhow!("body fields can't be overridden in a raw body"));
+            }
+            if !headers.contains_key(header::CONTENT_TYPE) {
+                return Err(anyhow!("a raw body needs an explicit content-type header"));
+            }
+            return Ok((headers, query, raw));
+        }
+
         if !headers.contains_key(header::CONTENT_TYPE) {
             headers.insert(
                 header::CONTENT_TYPE,
@@ -115,26 +488,34 @@ impl RequestProfile {
             );
         }
 
-        for (k, v) in &args.query {
-            query[k] = v.parse()?;
-        }
-
         for (k, v) in &args.body {
-            body[k] = v.parse()?;
+            body[k] = v.clone();
         }
 
         let content_type = get_content_type(&headers);
 
         match content_type.as_deref() {
             Some("application/json") => {
-                let body = serde_json::to_string(&body)?;
+                let body = serde_json::to_vec(&body)?;
+                Ok((headers, query, body))
+            }
+            Some("application/x-www-form-urlencoded") => {
+                let body = form::encode_urlencoded(&body)?;
                 Ok((headers, query, body))
             }
-            Some("application/x-ww-form-urlencoded" | "multipart/from-data") => {
-                let body = serde_urlencoded::to_string(&body)?;
+            Some("multipart/form-data") => {
+                let boundary = form::generate_boundary();
+                let body = form::encode_multipart(&body, &boundary)?;
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_str(&format!("multipart/form-data; boundary={}", boundary))?,
+                );
                 Ok((headers, query, body))
             }
-            _ => Err(anyhow::anyhow!("unsupported content-type")),
+            v => Err(anyhow!(
+                "unsupported content-type: {}",
+                v.unwrap_or_default()
+            )),
         }
     }
 }
@@ -157,19 +538,58 @@ impl ValidateConfig for RequestProfile {
                 ));
             }
         }
+        let body_sources = [
+            !empty_json_value(&self.body),
+            self.body_raw.is_some(),
+            self.body_file.is_some(),
+        ];
+        if body_sources.iter().filter(|v| **v).count() > 1 {
+            return Err(anyhow!(
+                "Only one of body, body_raw and body_file could be set"
+            ));
+        }
+        if (self.body_raw.is_some() || self.body_file.is_some())
+            && !self.headers.contains_key(header::CONTENT_TYPE)
+        {
+            return Err(anyhow!(
+                "body_raw and body_file need an explicit content-type header"
+            ));
+        }
+        if let Some(transport) = self.transport.as_ref() {
+            transport.validate()?;
+        }
+        for target in self.resolve.keys() {
+            parse_resolve_target(target)?;
+        }
+        // the values could still come from `-e`, only the placeholders are checked here
+        path::path_param_names(&decode_braces(self.url.path()))?;
+        if self.socket.is_some() && self.url.scheme() == "unix" {
+            return Err(anyhow!("socket can't be set with a unix url"));
+        }
+        if cfg!(not(unix)) && (self.socket.is_some() || self.url.scheme() == "unix") {
+            return Err(anyhow!("unix sockets are only supported on unix"));
+        }
+        for (idx, step) in self.setup.iter().enumerate() {
+            if !step.request.setup.is_empty() {
+                return Err(anyhow!("setup step {} can't have its own setup", idx + 1));
+            }
+            step.request
+                .validate()
+                .with_context(|| format!("setup step {} failed to validate", idx + 1))?;
+        }
         Ok(())
     }
 }
 
 impl FromStr for RequestProfile {
     type Err = Error;
+    /// a url, or a whole curl command like `curl -X POST -H 'a: b' https://...`
     fn from_str(s: &str) -> Result<Self> {
-        let mut url = Url::parse(s)?;
-        let qs = url.query_pairs();
-        let mut params = json!({});
-        for (k, v) in qs {
-            params[&*k] = v.parse()?;
+        if s.trim_start().starts_with("curl ") {
+            return parse_curl(s);
         }
+        let mut url = Url::parse(s)?;
+        let params = query_params(&url);
         url.set_query(None);
         Ok(RequestProfile::new(
             Method::GET,
@@ -181,29 +601,91 @@ impl FromStr for RequestProfile {
     }
 }
 
+/// the query string of a url as a params object
+fn query_params(url: &Url) -> serde_json::Value {
+    let mut params = json!({});
+    for (k, v) in url.query_pairs() {
+        let v = parse_value(&v);
+        // repeated keys are read back as an array, as the default query style writes them
+        match params.get_mut(&*k) {
+            Some(serde_json::Value::Array(values)) => values.push(v),
+            Some(prev) => *prev = json!([prev.take(), v]),
+            None => params[&*k] = v,
+        }
+    }
+    params
+}
+
 impl ResponseExt {
     pub fn into_inner(self) -> Response {
-        self.0
+        self.res
+    }
+    /// the url the request was sent to, rendered and with its path params and query, a
+    /// socket request has it as `http://localhost`
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+    /// how many times the request was sent, retries included
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+    /// the redirects followed before the final response
+    pub fn redirects(&self) -> &[RedirectHop] {
+        &self.redirects
+    }
+    /// the content encoding the body was decoded from, if any
+    pub fn encoding(&self) -> Option<&EncodingReport> {
+        self.encoding.as_ref()
     }
     pub async fn get_text(self, profile: &ResponseProfile) -> Result<String> {
-        let res = self.0;
-        let mut output = get_status_text(&res)?;
+        Ok(self.get_checked_text(profile, None, None).await?.0)
+    }
+    /// the text to diff, and the violations of the contract if there's one
+    ///
+    /// the base urls are rewritten in the redirects, headers and body, but not in what
+    /// the contract checks.
+    pub(crate) async fn get_checked_text(
+        self,
+        profile: &ResponseProfile,
+        contract: Option<&LoadedContract>,
+        rewriter: Option<&UrlRewriter>,
+    ) -> Result<(String, Vec<String>)> {
+        let rewrite = |text: String| match rewriter {
+            Some(rewriter) => rewriter.rewrite(&text),
+            None => text,
+        };
+        let mut output = rewrite(get_redirect_text(&self.redirects)?);
+        let res = self.res;
+        if profile.skip_version {
+            write!(&mut output, "{}", res.status())?;
+        } else {
+            write!(&mut output, "{}", get_status_text(&res)?)?;
+        }
 
         write!(
             &mut output,
             "{}",
-            get_header_text(&res, &profile.skip_headers)?
+            rewrite(get_header_text(&res, &profile.skip_headers)?)
         )?;
 
-        write!(
-            &mut output,
-            "{}",
-            get_body_text(res, &profile.skip_body).await?
-        )?;
-        Ok(output)
+        let status = res.status();
+        let content_type = get_content_type(res.headers());
+        let text = res.text().await?;
+        let violations = match contract {
+            Some(contract) => contract.check(status, &text),
+            None => vec![],
+        };
+        let text = rewrite(text);
+        let body = if profile.schema_only {
+            schema_text(content_type.as_deref(), &text, &profile.skip_body)?
+        } else {
+            body_text(content_type.as_deref(), &text, &profile.skip_body)?
+        };
+        write!(&mut output, "{}", body)?;
+        Ok((output, violations))
     }
     pub fn get_header_keys(&self) -> Vec<String> {
-        let res = &self.0;
+        let res = &self.res;
         let headers = res.headers();
         headers
             .iter()
@@ -212,21 +694,44 @@ impl ResponseExt {
     }
 }
 pub async fn get_body_text(res: Response, skip_body: &[String]) -> Result<String> {
-    let mut output = String::new();
     let content_type = get_content_type(res.headers());
     let text = res.text().await?;
-    match content_type.as_deref() {
+    body_text(content_type.as_deref(), &text, skip_body)
+}
+
+/// the inferred schema of a json body, other bodies only by their content type
+pub async fn get_schema_text(res: Response, skip_body: &[String]) -> Result<String> {
+    let content_type = get_content_type(res.headers());
+    let text = res.text().await?;
+    schema_text(content_type.as_deref(), &text, skip_body)
+}
+
+fn body_text(content_type: Option<&str>, text: &str, skip_body: &[String]) -> Result<String> {
+    match content_type {
+        Some("application/json") => filter_json(text, skip_body),
+        _ => Ok(text.to_string()),
+    }
+}
+
+fn schema_text(content_type: Option<&str>, text: &str, skip_body: &[String]) -> Result<String> {
+    match content_type {
         Some("application/json") => {
-            let text = filter_json(&text, skip_body)?;
-            write!(&mut output, "{}", text)?;
-        }
-        _ => {
-            write!(&mut output, "{}", text)?;
+            let json = parse_json(text, skip_body)?;
+            schema::infer_schema(&json)
         }
+        Some(content_type) => Ok(format!("{} body\n", content_type)),
+        None if text.is_empty() => Ok(String::new()),
+        None => Ok("untyped body\n".to_string()),
     }
-    Ok(output)
 }
 
+pub fn get_redirect_text(redirects: &[RedirectHop]) -> Result<String> {
+    let mut output = String::new();
+    for hop in redirects {
+        writeln!(&mut output, "redirect: {} -> {}", hop.status, hop.location)?;
+    }
+    Ok(output)
+}
 pub fn get_status_text(res: &Response) -> Result<String> {
     Ok(format!("{:?} {}", res.version(), res.status()))
 }
@@ -245,6 +750,12 @@ pub fn get_header_text(res: &Response, skip_headers: &[String]) -> Result<String
 }
 
 fn filter_json(text: &str, skip: &[String]) -> Result<String> {
+    let json = parse_json(text, skip)?;
+    Ok(serde_json::to_string_pretty(&json)?)
+}
+
+/// parse a json body without the top level `skip` fields
+fn parse_json(text: &str, skip: &[String]) -> Result<serde_json::Value> {
     let mut json: serde_json::Value = serde_json::from_str(text)?;
 
     if let serde_json::Value::Object(ref mut obj) = json {
@@ -253,7 +764,40 @@ fn filter_json(text: &str, skip: &[String]) -> Result<String> {
         }
     }
 
-    Ok(serde_json::to_string_pretty(&json)?)
+    Ok(json)
+}
+
+/// a json value if `s` is one, like `42` or `true`, otherwise the string itself
+pub(crate) fn parse_value(s: &str) -> serde_json::Value {
+    s.parse()
+        .unwrap_or_else(|_| serde_json::Value::String(s.to_string()))
+}
+
+/// `{` and `}` in the path are percent-encoded by `Url`
+fn decode_braces(s: &str) -> String {
+    s.replace("%7B", "{").replace("%7D", "}")
+}
+
+/// write the `{name}` placeholders as they were written
+fn serialize_url<S: serde::Serializer>(url: &Url, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&decode_braces(url.as_str()))
+}
+
+/// split a resolve key like `api.example.com:443` into its host and optional port
+fn parse_resolve_target(target: &str) -> Result<(&str, Option<u16>)> {
+    let (host, port) = match target.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .with_context(|| format!("invalid port in resolve target: {}", target))?;
+            (host, Some(port))
+        }
+        None => (target, None),
+    };
+    if host.is_empty() {
+        return Err(anyhow!("invalid resolve target: {}", target));
+    }
+    Ok((host, port))
 }
 
 fn get_content_type(headers: &HeaderMap) -> Option<String> {
@@ -264,15 +808,24 @@ fn get_content_type(headers: &HeaderMap) -> Option<String> {
 }
 
 fn empty_json_value(v: &Option<serde_json::Value>) -> bool {
-    v.as_ref().map_or(true, |v| {
-        v.is_null() || (v.is_object() && v.as_object().unwrap().is_empty())
-    })
+    v.as_ref()
+        .is_none_or(|v| v.is_null() || (v.is_object() && v.as_object().unwrap().is_empty()))
 }
 
 pub fn is_default<T: Default + PartialEq>(v: &T) -> bool {
     v == &T::default()
 }
 
+/// settings of `transport` win field by field, so a profile only setting a proxy keeps
+/// the default `http_version`
+fn merge_transport(transport: &mut Option<Transport>, other: Option<&Transport>) {
+    match (transport.as_mut(), other) {
+        (Some(transport), Some(other)) => transport.merge(other),
+        (None, Some(other)) => *transport = Some(other.clone()),
+        (_, None) => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,6 +871,92 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn request_profile_send_should_request_the_displayed_url() {
+        let _m = mock_for_url(
+            "/todo-query?filter%5Bdone%5D=false&ids%5B%5D=1&ids%5B%5D=2",
+            json!({"id": 1}),
+        );
+
+        let mut profile = get_profile("/todo-query");
+        profile.params = Some(json!({"ids": [1, 2], "filter": {"done": false}}));
+        profile.query_style = Some(QueryStyle::Brackets);
+        assert_eq!(
+            profile.get_url(&Default::default()).unwrap(),
+            get_url("/todo-query?filter%5Bdone%5D=false&ids%5B%5D=1&ids%5B%5D=2")
+        );
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_path_params_should_work() {
+        let _m = mock_for_url("/users/42/orders/a%2Fb", json!({"id": 1}));
+
+        let mut profile = get_profile("/users/{user_id}/orders/{order_id}");
+        profile.path_params = [("user_id".to_string(), "1".to_string())]
+            .into_iter()
+            .collect();
+        assert!(profile.validate().is_ok());
+        let err = profile.get_url(&Default::default()).unwrap_err();
+        assert!(err.to_string().contains("unfilled placeholder {order_id}"));
+        profile.path_params.insert("order_id".into(), "".into());
+        assert!(profile.get_url(&Default::default()).is_err());
+
+        let args = ExtraArgs::new_with_path(vec![
+            ("user_id".into(), "42".into()),
+            ("order_id".into(), "a/b".into()),
+        ]);
+        assert_eq!(
+            profile.get_url(&args).unwrap(),
+            get_url("/users/42/orders/a%2Fb")
+        );
+        let res = profile.send(&args).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[test]
+    fn request_profile_from_str_should_read_repeated_keys() {
+        let profile = get_profile("/todo?a=1&a=2&b=3");
+        assert_eq!(profile.params, Some(json!({"a": [1, 2], "b": 3})));
+        assert_eq!(
+            profile.get_url(&Default::default()).unwrap(),
+            get_url("/todo?a=1&a=2&b=3")
+        );
+    }
+
+    #[test]
+    fn response_profile_should_skip_recorded_headers() {
+        let mut profile = ResponseProfile::snapshot();
+        profile.skip_headers.push("x-trace".into());
+        let recorded = "redirect: 302 Found -> /todos/1\n\
+                        404 Not Founddate: \"Mon\"\n\
+                        content-type: \"application/json\"\n\
+                        x-trace: \"abc\"\n\n\
+                        x-trace: \"body\"";
+        assert_eq!(
+            profile.skip_recorded_headers(recorded),
+            "redirect: 302 Found -> /todos/1\n\
+             404 Not Foundcontent-type: \"application/json\"\n\n\
+             x-trace: \"body\""
+        );
+    }
+
+    #[test]
+    fn request_profile_should_read_plain_values_as_strings() {
+        assert_eq!(parse_value("42"), json!(42));
+        assert_eq!(parse_value("true"), json!(true));
+        assert_eq!(parse_value("hello world"), json!("hello world"));
+
+        let profile = get_profile("/todo?q=hello&page=2");
+        assert_eq!(profile.params, Some(json!({"q": "hello", "page": 2})));
+        let args = ExtraArgs::new_with_query(vec![("q".into(), "bye".into())]);
+        assert_eq!(
+            profile.get_url(&args).unwrap(),
+            get_url("/todo?page=2&q=bye")
+        );
+    }
+
     #[tokio::test]
     async fn get_status_text_should_work() {
         let _m = mock_for_url("/todo", json!({"id": 1, "title": "todo"}));
@@ -357,6 +996,20 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn get_schema_text_should_work() {
+        let _m = mock_for_url("/todo/schema", json!({"id": 1, "title": null}));
+
+        let res = get_response("/todo/schema", &Default::default())
+            .await
+            .into_inner();
+
+        assert_eq!(
+            get_schema_text(res, &["id".into()]).await.unwrap(),
+            "$: object\n$.title: null\n"
+        )
+    }
+
     #[test]
     fn request_profile_validate_should_work() {
         let profile = get_profile("/todo?a=1&b=2");
@@ -404,7 +1057,610 @@ mod tests {
         sorted_header_keys.sort();
         let mut expected_header_keys = vec!["content-length", "content-type", "connection"];
         expected_header_keys.sort();
-        assert_eq!(sorted_header_keys,expected_header_keys);
+        assert_eq!(sorted_header_keys, expected_header_keys);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_basic_auth_should_work() {
+        let _m = mock("GET", "/basic-auth")
+            .match_header("authorization", "Basic dXNlcjpwYXNz")
+            .with_status(200)
+            .create();
+
+        let mut profile = get_profile("/basic-auth");
+        profile.auth = Some(Auth::Basic {
+            username: "user".into(),
+            password: Some(Secret::Value("pass".into())),
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_digest_auth_should_work() {
+        let _ok = mock("GET", "/digest-auth")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex(r#"^Digest username="user", realm="xdiff""#.into()),
+            )
+            .with_status(200)
+            .create();
+        let _challenge = mock("GET", "/digest-auth")
+            .with_status(401)
+            .with_header(
+                "www-authenticate",
+                r#"Digest realm="xdiff", qop="auth", nonce="abc""#,
+            )
+            .create();
+
+        let mut profile = get_profile("/digest-auth");
+        profile.auth = Some(Auth::Digest {
+            username: "user".into(),
+            password: Secret::Value("pass".into()),
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_hmac_signing_should_work() {
+        let _m = mock("GET", "/hmac-signed")
+            .match_header(
+                "x-signature",
+                mockito::Matcher::Regex("^[0-9a-f]{64}$".into()),
+            )
+            .with_status(200)
+            .create();
+
+        let mut profile = get_profile("/hmac-signed");
+        profile.sign = Some(Signing::Hmac(HmacSigning {
+            key: Secret::Value("secret".into()),
+            algorithm: HmacAlgorithm::Sha256,
+            header: "x-signature".into(),
+            canonical: "{method}\n{path}\n{timestamp}".into(),
+            encoding: SignatureEncoding::Hex,
+            prefix: String::new(),
+            timestamp_header: Some("x-timestamp".into()),
+        }));
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_should_keep_cookies_in_jar() {
+        let _login = mock("GET", "/cookie-login")
+            .with_status(200)
+            .with_header("set-cookie", "session=abc; Path=/")
+            .create();
+        let _m = mock("GET", "/cookie-todo")
+            .match_header("cookie", "session=abc")
+            .with_status(200)
+            .create();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut login = get_profile("/cookie-login");
+        login.cookie_jar = Some(dir.path().join("cookies.json"));
+        login.send(&Default::default()).await.unwrap();
+
+        let mut profile = get_profile("/cookie-todo");
+        profile.cookie_jar = login.cookie_jar.clone();
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_setup_should_work() {
+        let _login = mock("POST", "/chain-login")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"data": {"token": "abc"}}"#)
+            .create();
+        let _m = mock("GET", "/chain-todo")
+            .match_header("authorization", "Bearer abc")
+            .with_status(200)
+            .create();
+
+        let profile: RequestProfile = serde_yaml::from_str(&format!(
+            r#"
+url: {}
+headers:
+  authorization: "Bearer {{{{token}}}}"
+setup:
+  - method: POST
+    url: {}
+    capture:
+      token:
+        json: $.data.token
+"#,
+            get_url("/chain-todo"),
+            get_url("/chain-login")
+        ))
+        .unwrap();
+        assert!(profile.validate().is_ok());
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_form_should_work() {
+        let _m = mock("POST", "/form")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body("completed=false&title=hello+world")
+            .with_status(200)
+            .create();
+
+        let mut profile = get_profile("/form");
+        profile.method = Method::POST;
+        profile.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        profile.body = Some(json!({"title": "hello world", "completed": false}));
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[test]
+    fn request_profile_generate_multipart_should_set_boundary() {
+        let mut profile = get_profile("/multipart");
+        profile.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("multipart/form-data"),
+        );
+        profile.body = Some(json!({"name": "kyros"}));
+        let (headers, _, body) = profile.generate(&Default::default()).unwrap();
+        let content_type = headers[header::CONTENT_TYPE].to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        assert!(String::from_utf8(body)
+            .unwrap()
+            .ends_with(&format!("--{}--\r\n", boundary)));
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_raw_body_should_work() {
+        let _m = mock("POST", "/raw-body")
+            .match_header("content-type", "text/csv")
+            .match_body("id,title\n1,todo\n")
+            .with_status(200)
+            .create();
+
+        let profile: RequestProfile = serde_yaml::from_str(&format!(
+            "url: {}\nmethod: POST\nheaders:\n  content-type: text/csv\nbody_raw: |\n  id,title\n  1,todo\n",
+            get_url("/raw-body")
+        ))
+        .unwrap();
+        assert!(profile.validate().is_ok());
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[test]
+    fn request_profile_generate_with_stdin_body_should_need_content_type() {
+        let profile = get_profile("/raw-body");
+        let args = ExtraArgs {
+            body_raw: Some(b"<todo/>".to_vec()),
+            ..Default::default()
+        };
+        assert!(profile.generate(&args).is_err());
+
+        let args = ExtraArgs {
+            headers: vec![("content-type".into(), "application/xml".into())],
+            ..args
+        };
+        let (_, _, body) = profile.generate(&args).unwrap();
+        assert_eq!(body, b"<todo/>");
+    }
+
+    #[test]
+    fn request_profile_with_raw_body_but_no_content_type_should_fail() {
+        let mut profile = get_profile("/raw-body");
+        profile.body_raw = Some("hello".into());
+        assert!(profile.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_should_retry_on_status() {
+        let unavailable = mock("GET", "/retry").with_status(503).expect(1).create();
+        let ok = mock("GET", "/retry").with_status(200).expect(1).create();
+
+        let mut profile = get_profile("/retry");
+        profile.retry = Some(RetryPolicy {
+            count: 2,
+            backoff: std::time::Duration::from_millis(1),
+            ..Default::default()
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.attempts(), 2);
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+        unavailable.assert();
+        ok.assert();
+
+        let unavailable = mock("POST", "/retry").with_status(503).expect(1).create();
+        profile.method = Method::POST;
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.attempts(), 1);
+        assert_eq!(
+            res.into_inner().status(),
+            reqwest::StatusCode::SERVICE_UNAVAILABLE
+        );
+        unavailable.assert();
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_should_record_redirects() {
+        let _m1 = mock("GET", "/redirect/from")
+            .with_status(301)
+            .with_header("location", "/redirect/via")
+            .create();
+        let _m2 = mock("GET", "/redirect/via")
+            .with_status(302)
+            .with_header("location", "/redirect/to")
+            .create();
+        let _m3 = mock("GET", "/redirect/to").with_status(200).create();
+
+        let res = get_response("/redirect/from", &Default::default()).await;
+        let hops: Vec<_> = res
+            .redirects()
+            .iter()
+            .map(|hop| (hop.status.as_u16(), hop.location.path().to_string()))
+            .collect();
+        assert_eq!(
+            hops,
+            vec![(301, "/redirect/via".into()), (302, "/redirect/to".into())]
+        );
+        let text = res.get_text(&Default::default()).await.unwrap();
+        assert!(text.starts_with(&format!(
+            "redirect: 301 Moved Permanently -> {}\n",
+            get_url("/redirect/via")
+        )));
+
+        let mut profile = get_profile("/redirect/from");
+        profile.redirect = Some(Redirect::Max(1));
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.redirects().len(), 1);
+        assert_eq!(res.into_inner().status(), StatusCode::FOUND);
+
+        profile.redirect = Some(Redirect::Max(0));
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert!(res.redirects().is_empty());
+        assert_eq!(res.into_inner().status(), StatusCode::MOVED_PERMANENTLY);
+
+        profile.redirect = Some(Redirect::Mode(RedirectMode::None));
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert!(res.redirects().is_empty());
+        assert_eq!(res.into_inner().status(), StatusCode::MOVED_PERMANENTLY);
+    }
+
+    #[tokio::test]
+    async fn response_get_text_should_skip_version() {
+        let _m = mock("GET", "/http1").with_status(200).create();
+
+        let mut profile = get_profile("/http1");
+        profile.transport = Some(Transport {
+            http_version: Some(HttpVersion::Http1),
+            ..Default::default()
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        let text = res
+            .get_text(&ResponseProfile {
+                skip_version: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(text.starts_with("200 OK"));
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_should_decode_compressed_body() {
+        use std::io::Write as _;
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(br#"{"id": 1, "title": "todo"}"#).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let _m = mock("GET", "/gzip")
+            .match_header("accept-encoding", "gzip")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("content-encoding", "gzip")
+            .with_body(&gzip)
+            .create();
+
+        let mut profile = get_profile("/gzip");
+        profile.compression = Some(Compression {
+            accept: vec![Encoding::Gzip],
+            decode: true,
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(
+            res.encoding(),
+            Some(&EncodingReport {
+                encoding: "gzip".into(),
+                compressed: gzip.len(),
+                decoded: 26,
+            })
+        );
+        let res = res.into_inner();
+        assert_eq!(res.url().as_str(), get_url("/gzip"));
+        assert!(res.headers().get("content-encoding").is_none());
+        assert!(res.headers().get("content-length").is_none());
+
+        assert_eq!(
+            get_body_text(res, &[]).await.unwrap(),
+            "{\n  \"id\": 1,\n  \"title\": \"todo\"\n}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn request_profile_send_over_unix_socket_should_work() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                      content-length: 11\r\n\r\n{\"id\": 42}\n",
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let profile: RequestProfile = format!("unix://{}:/todos?id=42", socket.display())
+            .parse()
+            .unwrap();
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.url().as_str(), "http://localhost/todos?id=42");
+        let res = res.into_inner();
+        assert_eq!(res.url().as_str(), "http://localhost/todos?id=42");
+        let body = get_body_text(res, &[]).await.unwrap();
+        assert_eq!(body, "{\n  \"id\": 42\n}");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /todos?id=42 HTTP/1.1\r\n"));
+        assert!(request.contains("host: localhost\r\n"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn request_profile_send_over_unix_socket_should_time_out_reading_body() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 11\r\n\r\n{\"id\"")
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        });
+
+        let mut profile: RequestProfile = format!("unix://{}:/todos", socket.display())
+            .parse()
+            .unwrap();
+        profile.timeout = Some(Timeouts {
+            total: Some(std::time::Duration::from_millis(200)),
+            ..Default::default()
+        });
+        let start = std::time::Instant::now();
+        assert!(profile.send(&Default::default()).await.is_err());
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn request_profile_digest_over_unix_socket_should_keep_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("digest.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let server = tokio::spawn(async move {
+            let responses: [&[u8]; 2] = [
+                b"HTTP/1.1 401 Unauthorized\r\n\
+                  www-authenticate: Digest realm=\"xdiff\", qop=\"auth\", nonce=\"abc\"\r\n\
+                  content-length: 0\r\n\r\n",
+                b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n",
+            ];
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                stream.write_all(response).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).to_string());
+            }
+            requests
+        });
+
+        let mut profile: RequestProfile = format!("unix://{}:/todos/{{id}}", socket.display())
+            .parse()
+            .unwrap();
+        profile.path_params.insert("id".into(), "42".into());
+        profile.params = Some(json!({"done": true}));
+        profile.auth = Some(Auth::Digest {
+            username: "user".into(),
+            password: Secret::Value("pass".into()),
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), StatusCode::OK);
+
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("GET /todos/42?done=true HTTP/1.1\r\n"));
+        assert!(requests[1].contains(r#"uri="/todos/42?done=true""#));
+    }
+
+    #[test]
+    fn config_defaults_should_apply_to_profiles() {
+        let config = RequestConfig::from_yaml(
+            r#"
+defaults:
+  timeout:
+    total: 5s
+  retry:
+    count: 3
+  transport:
+    http_version: http1
+  cookie_jar: /tmp/cookies.json
+todo:
+  url: http://localhost/todo
+todo-no-retry:
+  url: http://localhost/todo
+  retry:
+    count: 0
+  transport:
+    proxy: http://localhost:3128
+"#,
+        )
+        .unwrap();
+        let todo = config.get_profile("todo").unwrap();
+        assert_eq!(
+            todo.timeout.unwrap().total,
+            Some(std::time::Duration::from_secs(5))
+        );
+        assert_eq!(todo.retry.as_ref().unwrap().count, 3);
+        assert_eq!(todo.cookie_jar, Some(PathBuf::from("/tmp/cookies.json")));
+        let todo = config.get_profile("todo-no-retry").unwrap();
+        assert_eq!(todo.retry.as_ref().unwrap().count, 0);
+        let transport = todo.transport.as_ref().unwrap();
+        assert!(transport.proxy.is_some());
+        assert_eq!(transport.http_version, Some(HttpVersion::Http1));
+    }
+
+    #[test]
+    fn diff_config_should_give_each_side_its_own_jar() {
+        let config = DiffConfig::from_yaml(
+            r#"
+defaults:
+  cookie_jar: /tmp/cookies.json
+todo:
+  req1:
+    url: http://localhost/todo
+  req2:
+    url: http://localhost:8080/todo
+"#,
+        )
+        .unwrap();
+        let todo = config.get_profile("todo").unwrap();
+        assert_eq!(
+            todo.req1.cookie_jar,
+            Some(PathBuf::from("/tmp/cookies.json.req1"))
+        );
+        assert_eq!(
+            todo.req2.cookie_jar,
+            Some(PathBuf::from("/tmp/cookies.json.req2"))
+        );
+
+        let err = DiffConfig::from_yaml(
+            r#"
+todo:
+  req1:
+    url: http://localhost/todo
+    cookie_jar: /tmp/cookies.json
+  req2:
+    url: http://localhost:8080/todo
+    cookie_jar: /tmp/cookies.json
+"#,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("req1 and req2 need their own cookie_jar"));
+    }
+
+    #[tokio::test]
+    async fn config_vars_should_fill_placeholders() {
+        let _m = mock("GET", "/vars-todo/42?tenant=acme")
+            .match_header("x-env", "staging")
+            .with_status(200)
+            .create();
+        let config = RequestConfig::from_yaml(&format!(
+            r#"
+defaults:
+  vars:
+    tenant: acme
+    env: prod
+todo:
+  url: {}/{{{{id}}}}
+  params:
+    tenant: "{{{{tenant}}}}"
+  headers:
+    x-env: "{{{{env}}}}"
+  vars:
+    id: "42"
+    env: staging
+"#,
+            get_url("/vars-todo")
+        ))
+        .unwrap();
+        let todo = config.get_profile("todo").unwrap();
+        let res = todo.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_through_proxy_should_work() {
+        let _m = mock(
+            "GET",
+            mockito::Matcher::Regex("proxied.invalid/todo".into()),
+        )
+        .with_status(200)
+        .create();
+
+        let mut profile: RequestProfile = "http://proxied.invalid/todo".parse().unwrap();
+        profile.transport = Some(Transport {
+            proxy: Some(Url::parse(&mockito::server_url()).unwrap()),
+            ..Default::default()
+        });
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_profile_send_with_resolve_should_work() {
+        let _m = mock("GET", "/resolved")
+            .match_header(
+                "host",
+                mockito::Matcher::Regex("^api.example.invalid:\\d+$".into()),
+            )
+            .with_status(200)
+            .create();
+
+        let server = Url::parse(&mockito::server_url()).unwrap();
+        let port = server.port().unwrap();
+        let mut profile: RequestProfile = format!("http://api.example.invalid:{}/resolved", port)
+            .parse()
+            .unwrap();
+        profile.resolve = [(
+            format!("api.example.invalid:{}", port),
+            "127.0.0.1".parse().unwrap(),
+        )]
+        .into_iter()
+        .collect();
+        assert!(profile.validate().is_ok());
+        let res = profile.send(&Default::default()).await.unwrap();
+        assert_eq!(res.into_inner().status(), reqwest::StatusCode::OK);
+    }
+
+    #[test]
+    fn parse_resolve_target_should_work() {
+        assert_eq!(
+            parse_resolve_target("api.example.com:443").unwrap(),
+            ("api.example.com", Some(443))
+        );
+        assert_eq!(
+            parse_resolve_target("api.example.com").unwrap(),
+            ("api.example.com", None)
+        );
+        assert!(parse_resolve_target("api.example.com:https").is_err());
     }
 
     #[test]
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::BTreeMap;
+
+/// everything but the unreserved characters, so a value stays in one path segment
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// the `{name}` placeholders in a path, `{{name}}` variables are skipped
+pub fn path_param_names(path: &str) -> Result<Vec<&str>> {
+    let mut names = vec![];
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        if rest[start..].starts_with("{{") {
+            rest = match rest[start..].find("}}") {
+                Some(end) => &rest[start + end + 2..],
+                None => return Err(anyhow!("unclosed variable in path: {}", path)),
+            };
+            continue;
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unclosed path param in path: {}", path))?;
+        let name = &rest[start + 1..start + end];
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(anyhow!("invalid path param {{{}}} in path: {}", name, path));
+        }
+        names.push(name);
+        rest = &rest[start + end + 1..];
+    }
+    Ok(names)
+}
+
+/// fill the `{name}` placeholders of a path with percent-encoded values, an empty value
+/// leaves the placeholder unfilled
+pub fn fill_path_params(path: &str, params: &BTreeMap<String, String>) -> Result<String> {
+    let mut output = path.to_string();
+    for name in path_param_names(path)? {
+        let value = params.get(name).filter(|v| !v.is_empty()).ok_or_else(|| {
+            anyhow!(
+                "unfilled placeholder {{{}}}, set it in path_params or with -e :{}=value",
+                name,
+                name
+            )
+        })?;
+        output = output.replace(
+            &format!("{{{}}}", name),
+            &utf8_percent_encode(value, SEGMENT).to_string(),
+        );
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_param_names_should_skip_variables() {
+        assert_eq!(
+            path_param_names("/users/{user_id}/orders/{{order}}/{order_id}").unwrap(),
+            vec!["user_id", "order_id"]
+        );
+        assert!(path_param_names("/users/{user id}").is_err());
+        assert!(path_param_names("/users/{user_id").is_err());
+    }
+
+    #[test]
+    fn fill_path_params_should_encode_values() {
+        let params: BTreeMap<_, _> = [("user_id".to_string(), "a/b c".to_string())]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            fill_path_params("/users/{user_id}/orders", &params).unwrap(),
+            "/users/a%2Fb%20c/orders"
+        );
+        assert!(fill_path_params("/users/{user_id}/orders/{order_id}", &params).is_err());
+        let params: BTreeMap<_, _> = [("user_id".to_string(), "".to_string())]
+            .into_iter()
+            .collect();
+        assert!(fill_path_params("/users/{user_id}/orders", &params).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How arrays in the query params are written, objects always use `a[b]=1`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryStyle {
+    /// `a=1&a=2`
+    #[default]
+    Repeat,
+    /// `a[]=1&a[]=2`
+    Brackets,
+    /// `a=1,2`
+    Comma,
+    /// `a[0]=1&a[1]=2`
+    Indexed,
+}
+
+/// flatten the query params object into (name, value) pairs in the given style
+pub fn query_pairs(query: &Value, style: QueryStyle) -> Result<Vec<(String, String)>> {
+    let obj = query
+        .as_object()
+        .ok_or_else(|| anyhow!("params must be an object"))?;
+    let mut pairs = vec![];
+    for (k, v) in obj {
+        flatten(k.clone(), v, style, &mut pairs)?;
+    }
+    Ok(pairs)
+}
+
+fn flatten(
+    name: String,
+    value: &Value,
+    style: QueryStyle,
+    pairs: &mut Vec<(String, String)>,
+) -> Result<()> {
+    match value {
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                flatten(format!("{}[{}]", name, k), v, style, pairs)?;
+            }
+        }
+        Value::Array(values) => match style {
+            QueryStyle::Repeat => {
+                for v in values {
+                    flatten(name.clone(), v, style, pairs)?;
+                }
+            }
+            QueryStyle::Brackets => {
+                for v in values {
+                    flatten(format!("{}[]", name), v, style, pairs)?;
+                }
+            }
+            QueryStyle::Indexed => {
+                for (idx, v) in values.iter().enumerate() {
+                    flatten(format!("{}[{}]", name, idx), v, style, pairs)?;
+                }
+            }
+            QueryStyle::Comma => {
+                let values = values
+                    .iter()
+                    .map(|v| match v {
+                        Value::Array(_) | Value::Object(_) => Err(anyhow!(
+                            "comma style only supports arrays of plain values: {}",
+                            name
+                        )),
+                        v => Ok(text_value(v)),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                pairs.push((name, values.join(",")));
+            }
+        },
+        v => pairs.push((name, text_value(v))),
+    }
+    Ok(())
+}
+
+fn text_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        v => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encode(query: &Value, style: QueryStyle) -> String {
+        query_pairs(query, style)
+            .unwrap()
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    #[test]
+    fn query_pairs_should_follow_style() {
+        let query = json!({"a": [1, 2], "f": {"done": false}, "q": "hi"});
+        assert_eq!(
+            encode(&query, QueryStyle::Repeat),
+            "a=1&a=2&f[done]=false&q=hi"
+        );
+        assert_eq!(
+            encode(&query, QueryStyle::Brackets),
+            "a[]=1&a[]=2&f[done]=false&q=hi"
+        );
+        assert_eq!(
+            encode(&query, QueryStyle::Comma),
+            "a=1,2&f[done]=false&q=hi"
+        );
+        assert_eq!(
+            encode(&query, QueryStyle::Indexed),
+            "a[0]=1&a[1]=2&f[done]=false&q=hi"
+        );
+    }
+
+    #[test]
+    fn comma_style_with_nested_array_should_fail() {
+        let query = json!({"a": [[1], [2]]});
+        assert!(query_pairs(&query, QueryStyle::Comma).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use reqwest::{redirect::Policy, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// How redirects are handled: `follow`, `none`, or the max number of hops to follow
+///
+/// with a max, the redirect response past the limit is returned instead of an error, so
+/// both sides could still be compared.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Redirect {
+    Max(usize),
+    Mode(RedirectMode),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedirectMode {
+    /// follow up to 10 hops, like reqwest does
+    Follow,
+    /// return the redirect response itself
+    None,
+}
+
+/// A redirect that was followed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    pub status: StatusCode,
+    pub location: Url,
+}
+
+pub type RedirectChain = Arc<Mutex<Vec<RedirectHop>>>;
+
+const DEFAULT_MAX_HOPS: usize = 10;
+
+impl Redirect {
+    pub fn max_hops(&self) -> usize {
+        match self {
+            Redirect::Max(max) => *max,
+            Redirect::Mode(RedirectMode::Follow) => DEFAULT_MAX_HOPS,
+            Redirect::Mode(RedirectMode::None) => 0,
+        }
+    }
+
+    /// a reqwest policy which records every hop it follows into `chain`
+    pub fn policy(&self, chain: RedirectChain) -> Policy {
+        let max = self.max_hops();
+        if max == 0 {
+            return Policy::none();
+        }
+        let stop = matches!(self, Redirect::Max(_));
+        Policy::custom(move |attempt| {
+            // the first url of the request is in `previous` as well
+            if attempt.previous().len() > max {
+                if stop {
+                    return attempt.stop();
+                }
+                return attempt.error(anyhow!("too many redirects, max {}", max));
+            }
+            chain.lock().unwrap().push(RedirectHop {
+                status: attempt.status(),
+                location: attempt.url().clone(),
+            });
+            attempt.follow()
+        })
+    }
+}
+
+impl Default for Redirect {
+    fn default() -> Self {
+        Redirect::Mode(RedirectMode::Follow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_should_deserialize() {
+        let redirect: Redirect = serde_yaml::from_str("follow").unwrap();
+        assert_eq!(redirect.max_hops(), 10);
+        let redirect: Redirect = serde_yaml::from_str("none").unwrap();
+        assert_eq!(redirect, Redirect::Mode(RedirectMode::None));
+        let redirect: Redirect = serde_yaml::from_str("3").unwrap();
+        assert_eq!(redirect.max_hops(), 3);
+    }
+}
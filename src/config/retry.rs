@@ -0,0 +1,139 @@
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Timeouts of a request, e.g. `connect: 2s` and `total: 30s`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timeouts {
+    #[serde(
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub connect: Option<Duration>,
+    /// from sending the request until the whole body is read
+    #[serde(
+        with = "humantime_serde",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub total: Option<Duration>,
+}
+
+/// How to retry a request which failed to connect, timed out, or got a retryable status
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// retries after the first attempt
+    pub count: u32,
+    #[serde(default = "default_statuses")]
+    pub statuses: Vec<u16>,
+    /// delay before the first retry, doubled for each of the following ones
+    #[serde(with = "humantime_serde", default = "default_backoff")]
+    pub backoff: Duration,
+    #[serde(with = "humantime_serde", default = "default_max_backoff")]
+    pub max_backoff: Duration,
+    /// randomize the delays between half and all of their value
+    #[serde(default = "default_jitter")]
+    pub jitter: bool,
+    /// retry `statuses` for non-idempotent methods like POST too, which the server may
+    /// have handled already
+    #[serde(skip_serializing_if = "crate::config::is_default", default)]
+    pub all_methods: bool,
+}
+
+impl RetryPolicy {
+    pub fn should_retry_status(&self, method: &Method, status: StatusCode) -> bool {
+        (self.all_methods || method.is_idempotent()) && self.statuses.contains(&status.as_u16())
+    }
+
+    pub fn should_retry_error(&self, err: &anyhow::Error) -> bool {
+        if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+            e.is_timeout() || e.is_connect()
+        } else if let Some(e) = err.downcast_ref::<hyper::Error>() {
+            e.is_timeout() || e.is_connect()
+        } else {
+            err.is::<tokio::time::error::Elapsed>()
+        }
+    }
+
+    /// delay before the given retry, starting from 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        if self.jitter {
+            delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            statuses: default_statuses(),
+            backoff: default_backoff(),
+            max_backoff: default_max_backoff(),
+            jitter: default_jitter(),
+            all_methods: false,
+        }
+    }
+}
+
+fn default_statuses() -> Vec<u16> {
+    vec![429, 502, 503, 504]
+}
+
+fn default_backoff() -> Duration {
+    Duration::from_millis(200)
+}
+
+fn default_max_backoff() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_jitter() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_policy_should_deserialize_with_defaults() {
+        let policy: RetryPolicy = serde_yaml::from_str("count: 2\nbackoff: 1s").unwrap();
+        assert_eq!(
+            policy,
+            RetryPolicy {
+                count: 2,
+                backoff: Duration::from_secs(1),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn retry_delay_should_back_off_exponentially() {
+        let policy = RetryPolicy {
+            count: 5,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: false,
+            ..Default::default()
+        };
+        let delays: Vec<_> = (0..4).map(|i| policy.delay(i).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500]);
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        let delay = policy.delay(1).as_millis();
+        assert!((100..=200).contains(&delay));
+    }
+}
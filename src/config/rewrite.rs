@@ -0,0 +1,164 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PLACEHOLDER: &str = "{base}";
+
+/// Replace the base urls of both requests in the responses, so links to each side's own
+/// host (`Location`, `Link`, hrefs in the body) compare equal
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct BaseRewrite {
+    /// other hosts or base urls of the same service, like `cdn.staging.api`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub aliases: Vec<String>,
+    /// what the base urls are replaced with, `{base}` by default
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub placeholder: Option<String>,
+}
+
+/// The base urls to replace, longest first
+#[derive(Debug)]
+pub(crate) struct UrlRewriter {
+    prefixes: Vec<String>,
+    placeholder: String,
+}
+
+impl BaseRewrite {
+    /// `urls` are the urls the requests were sent to, a socket request gives
+    /// `http://localhost`
+    pub(crate) fn rewriter(&self, urls: &[&Url]) -> UrlRewriter {
+        let mut bases = vec![];
+        for url in urls {
+            if url.has_host() {
+                bases.push(url.origin().ascii_serialization());
+            }
+        }
+        for alias in &self.aliases {
+            let alias = alias.trim_end_matches('/');
+            if alias.contains("://") {
+                // the same form as the origins, e.g. without a default port
+                match Url::parse(alias) {
+                    Ok(url) => bases.push(url.as_str().trim_end_matches('/').to_string()),
+                    Err(_) => bases.push(alias.to_string()),
+                }
+            } else {
+                bases.push(format!("http://{}", alias));
+                bases.push(format!("https://{}", alias));
+            }
+        }
+        let mut prefixes = vec![];
+        for base in bases {
+            // links could give the default port, like `https://api.example.com:443`
+            prefixes.extend(with_default_port(&base));
+            prefixes.push(base);
+        }
+        // json may escape the slashes, like `https:\/\/api.example.com`
+        let escaped: Vec<_> = prefixes.iter().map(|p| p.replace('/', "\\/")).collect();
+        prefixes.extend(escaped);
+        prefixes.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        prefixes.dedup();
+        UrlRewriter {
+            prefixes,
+            placeholder: self
+                .placeholder
+                .clone()
+                .unwrap_or_else(|| DEFAULT_PLACEHOLDER.to_string()),
+        }
+    }
+}
+
+impl UrlRewriter {
+    pub(crate) fn rewrite(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for prefix in &self.prefixes {
+            text = replace_prefix(&text, prefix, &self.placeholder);
+        }
+        text
+    }
+}
+
+/// `base` with its default port written out, if it has none
+fn with_default_port(base: &str) -> Option<String> {
+    let url = Url::parse(base).ok()?;
+    let port = match url.port() {
+        Some(_) => return None,
+        None => url.port_or_known_default()?,
+    };
+    let path = match url.path() {
+        "/" => "",
+        path => path,
+    };
+    Some(format!(
+        "{}://{}:{}{}",
+        url.scheme(),
+        url.host_str()?,
+        port,
+        path
+    ))
+}
+
+/// replace `prefix` where it isn't followed by more of a host or a port, so
+/// `https://api.example.com` is kept in `https://api.example.com.cn`
+fn replace_prefix(text: &str, prefix: &str, placeholder: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find(prefix) {
+        let end = idx + prefix.len();
+        let whole = rest[end..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':')));
+        output.push_str(&rest[..idx]);
+        output.push_str(if whole { placeholder } else { prefix });
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewriter_should_replace_bases_and_aliases() {
+        let url1 = Url::parse("https://staging.api/x/1").unwrap();
+        let url2 = Url::parse("http://localhost:8080/x/1").unwrap();
+        let rewrite = BaseRewrite {
+            aliases: vec!["cdn.staging.api".into()],
+            ..Default::default()
+        };
+        let rewriter = rewrite.rewriter(&[&url1, &url2]);
+        let text = r#"{"self": "https://staging.api/x/1", "next": "http://localhost:8080/x/2", "img": "https:\/\/cdn.staging.api\/1.png"}"#;
+        assert_eq!(
+            rewriter.rewrite(text),
+            r#"{"self": "{base}/x/1", "next": "{base}/x/2", "img": "{base}\/1.png"}"#
+        );
+    }
+
+    #[test]
+    fn rewriter_should_match_default_ports() {
+        let url = Url::parse("https://staging.api:443/x").unwrap();
+        let rewrite = BaseRewrite {
+            aliases: vec!["http://cdn.staging.api:80/assets".into()],
+            ..Default::default()
+        };
+        let rewriter = rewrite.rewriter(&[&url]);
+        let text = "https://staging.api:443/a https://staging.api/b \
+                    http://cdn.staging.api/assets/1.png http://cdn.staging.api:80/assets/2.png";
+        assert_eq!(
+            rewriter.rewrite(text),
+            "{base}/a {base}/b {base}/1.png {base}/2.png"
+        );
+    }
+
+    #[test]
+    fn rewriter_should_keep_other_hosts() {
+        let url = Url::parse("https://staging.api").unwrap();
+        let rewriter = BaseRewrite::default().rewriter(&[&url]);
+        let text = "https://staging.api.cn/a https://staging.api:8443/b https://staging.api";
+        assert_eq!(
+            rewriter.rewrite(text),
+            "https://staging.api.cn/a https://staging.api:8443/b {base}"
+        );
+    }
+}
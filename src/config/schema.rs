@@ -0,0 +1,218 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// The types seen at a path of a json value
+#[derive(Debug, Default)]
+struct Shape {
+    types: BTreeSet<&'static str>,
+    /// how many values were seen here, to tell if a field is always there
+    seen: usize,
+    /// how many of them were objects
+    objects: usize,
+    /// the object the field belongs to, array elements have none
+    parent: Option<String>,
+}
+
+/// the shape of a json value, one `path: types` line per field
+///
+/// array elements are merged under `path[]`, a field missing from some of the objects
+/// is marked with `?`, and a field which is sometimes null gets `| null`, e.g.
+/// `$.items[].note?: null | string`. Keys which aren't plain names are quoted, like
+/// `$["a.b"]`.
+pub fn infer_schema(value: &Value) -> Result<String> {
+    let mut shapes = BTreeMap::new();
+    walk(value, "$".to_string(), None, &mut shapes);
+
+    let mut output = String::new();
+    for (path, shape) in shapes.iter() {
+        let optional = match shape.parent.as_ref().and_then(|p| shapes.get(p)) {
+            Some(parent) => shape.seen < parent.objects,
+            None => false,
+        };
+        let types: Vec<_> = shape.types.iter().copied().collect();
+        writeln!(
+            output,
+            "{}{}: {}",
+            path,
+            if optional { "?" } else { "" },
+            types.join(" | ")
+        )?;
+    }
+    Ok(output)
+}
+
+/// the elements of an array which is empty on one side are unknown rather than absent,
+/// so they're taken from the other side, where the array has some
+pub(crate) fn fill_unknown(text1: &str, text2: &str) -> (String, String) {
+    (fill_from(text1, text2), fill_from(text2, text1))
+}
+
+fn fill_from(text: &str, other: &str) -> String {
+    let paths: BTreeSet<_> = text.lines().filter_map(schema_path).collect();
+    // arrays without any element here
+    let empty: Vec<_> = text
+        .lines()
+        .filter_map(schema_line)
+        .filter(|(_, types)| types.split(" | ").any(|t| t == "array"))
+        .map(|(path, _)| format!("{}[]", path))
+        .filter(|elements| !paths.contains(elements.as_str()))
+        .collect();
+    let mut lines: BTreeMap<String, &str> = BTreeMap::new();
+    for line in other.lines() {
+        let path = match schema_path(line) {
+            Some(path) => path,
+            None => continue,
+        };
+        if empty.iter().any(|elements| is_within(path, elements)) {
+            lines.insert(path.to_string(), line);
+        }
+    }
+    if lines.is_empty() {
+        return text.to_string();
+    }
+
+    // schema lines are sorted by path, the filled ones go in between
+    let mut output = String::new();
+    for line in text.lines() {
+        if let Some(path) = schema_path(line) {
+            while let Some(entry) = lines.first_entry() {
+                if entry.key().as_str() >= path {
+                    break;
+                }
+                output.push_str(entry.remove());
+                output.push('\n');
+            }
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    for line in lines.values() {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// the path of a schema line, without the `?`, and its types
+fn schema_line(line: &str) -> Option<(&str, &str)> {
+    if !line.starts_with('$') {
+        return None;
+    }
+    let (path, types) = line.rsplit_once(": ")?;
+    Some((path.strip_suffix('?').unwrap_or(path), types))
+}
+
+fn schema_path(line: &str) -> Option<&str> {
+    schema_line(line).map(|(path, _)| path)
+}
+
+fn is_within(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with('['),
+        None => false,
+    }
+}
+
+fn walk(value: &Value, path: String, parent: Option<&str>, shapes: &mut BTreeMap<String, Shape>) {
+    let shape = shapes.entry(path.clone()).or_default();
+    shape.seen += 1;
+    shape.types.insert(type_name(value));
+    shape.parent = parent.map(|p| p.to_string());
+    match value {
+        Value::Object(obj) => {
+            shape.objects += 1;
+            for (k, v) in obj {
+                walk(v, field_path(&path, k), Some(&path), shapes);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                walk(item, format!("{}[]", path), None, shapes);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn field_path(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}[{}]", path, Value::String(key.to_string()))
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn infer_schema_should_merge_array_elements() {
+        let value = json!({
+            "id": 1,
+            "price": 9.5,
+            "items": [
+                {"name": "a", "note": null},
+                {"name": "b", "note": "fragile"},
+                {"name": "c"}
+            ],
+            "tags": [],
+            "a.b": {"c": true}
+        });
+        assert_eq!(
+            infer_schema(&value).unwrap(),
+            "$: object\n\
+             $.id: number\n\
+             $.items: array\n\
+             $.items[]: object\n\
+             $.items[].name: string\n\
+             $.items[].note?: null | string\n\
+             $.price: number\n\
+             $.tags: array\n\
+             $[\"a.b\"]: object\n\
+             $[\"a.b\"].c: boolean\n"
+        );
+    }
+
+    #[test]
+    fn infer_schema_should_ignore_values() {
+        let v1 = json!({"id": 1, "name": "a", "tags": ["x"]});
+        let v2 = json!({"id": 2, "name": "b", "tags": ["y", "z"]});
+        assert_eq!(infer_schema(&v1).unwrap(), infer_schema(&v2).unwrap());
+        let v3 = json!({"id": "2", "name": "b", "tags": ["y", "z"]});
+        assert_ne!(infer_schema(&v1).unwrap(), infer_schema(&v3).unwrap());
+    }
+
+    #[test]
+    fn fill_unknown_should_take_elements_of_empty_arrays() {
+        let v1 = json!({"id": 1, "items": [], "tags": ["x"]});
+        let v2 = json!({"id": 2, "items": [{"name": "a", "tags": ["y"]}], "tags": []});
+        let text1 = format!("200 OK\n{}", infer_schema(&v1).unwrap());
+        let text2 = format!("200 OK\n{}", infer_schema(&v2).unwrap());
+        let (text1, text2) = fill_unknown(&text1, &text2);
+        assert_eq!(text1, text2);
+
+        let v3 = json!({"id": ["3"], "items": [], "tags": []});
+        let text3 = infer_schema(&v3).unwrap();
+        let (text1, text3) = fill_unknown(&infer_schema(&v1).unwrap(), &text3);
+        assert_ne!(text1, text3);
+    }
+}
@@ -0,0 +1,207 @@
+use super::{body_bytes, hmac_sha256, RequestSigner};
+use crate::Secret;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{self, HeaderValue},
+    Request,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// AWS Signature Version 4, credentials default to the usual `AWS_*` env vars
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AwsSigV4 {
+    pub region: String,
+    pub service: String,
+    #[serde(default = "default_access_key")]
+    pub access_key: Secret,
+    #[serde(default = "default_secret_key")]
+    pub secret_key: Secret,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub session_token: Option<Secret>,
+}
+
+impl RequestSigner for AwsSigV4 {
+    fn sign_at(&self, req: &mut Request, now: DateTime<Utc>) -> Result<()> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(body_bytes(req)));
+
+        let host = match req.url().port() {
+            Some(port) => format!("{}:{}", req.url().host_str().unwrap_or_default(), port),
+            None => req.url().host_str().unwrap_or_default().to_string(),
+        };
+        let headers = req.headers_mut();
+        headers.insert(header::HOST, HeaderValue::from_str(&host)?);
+        headers.insert("x-amz-date", HeaderValue::from_str(&amz_date)?);
+        if let Some(token) = self.session_token.as_ref() {
+            let mut token = HeaderValue::from_str(&token.resolve()?)?;
+            token.set_sensitive(true);
+            headers.insert("x-amz-security-token", token);
+        }
+        if self.service == "s3" {
+            headers.insert(
+                "x-amz-content-sha256",
+                HeaderValue::from_str(&payload_hash)?,
+            );
+        }
+
+        // header names are already lower case in a `HeaderMap`
+        let mut canonical_headers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for (k, v) in req.headers().iter() {
+            canonical_headers
+                .entry(k.as_str())
+                .or_default()
+                .push(normalize_space(v.to_str()?));
+        }
+        let signed_headers = canonical_headers
+            .keys()
+            .copied()
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = canonical_headers
+            .iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v.join(",")))
+            .collect();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            req.method(),
+            self.canonical_uri(req),
+            canonical_query(req),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let secret = format!("AWS4{}", self.secret_key.resolve()?);
+        let key = hmac_sha256(secret.as_bytes(), date.as_bytes());
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, self.service.as_bytes());
+        let key = hmac_sha256(&key, b"aws4_request");
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let mut authorization = HeaderValue::from_str(&format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM,
+            self.access_key.resolve()?,
+            scope,
+            signed_headers,
+            signature
+        ))?;
+        authorization.set_sensitive(true);
+        req.headers_mut()
+            .insert(header::AUTHORIZATION, authorization);
+        Ok(())
+    }
+}
+
+impl AwsSigV4 {
+    fn canonical_uri(&self, req: &Request) -> String {
+        let path = req.url().path();
+        // every service but s3 expects the (already encoded) path to be encoded again
+        if self.service == "s3" {
+            path.to_string()
+        } else {
+            path.split('/')
+                .map(uri_encode)
+                .collect::<Vec<_>>()
+                .join("/")
+        }
+    }
+}
+
+fn canonical_query(req: &Request) -> String {
+    let mut pairs: Vec<(String, String)> = req
+        .url()
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// percent-encode everything but the unreserved characters of RFC 3986
+fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn normalize_space(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn default_access_key() -> Secret {
+    Secret::Env {
+        env: "AWS_ACCESS_KEY_ID".into(),
+    }
+}
+
+fn default_secret_key() -> Secret {
+    Secret::Env {
+        env: "AWS_SECRET_ACCESS_KEY".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::{Method, Url};
+
+    fn signer() -> AwsSigV4 {
+        AwsSigV4 {
+            region: "us-east-1".into(),
+            service: "service".into(),
+            access_key: Secret::Value("AKIDEXAMPLE".into()),
+            secret_key: Secret::Value("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into()),
+            session_token: None,
+        }
+    }
+
+    fn sign(url: &str) -> String {
+        let mut req = Request::new(Method::GET, Url::parse(url).unwrap());
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        signer().sign_at(&mut req, now).unwrap();
+        req.headers()["authorization"].to_str().unwrap().to_string()
+    }
+
+    // both from the aws signature v4 test suite
+    #[test]
+    fn aws_sigv4_get_vanilla_should_work() {
+        assert_eq!(
+            sign("https://example.amazonaws.com/"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn aws_sigv4_query_should_be_sorted() {
+        assert_eq!(
+            sign("https://example.amazonaws.com/?Param2=value2&Param1=value1"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use super::{body_bytes, hmac_sha256, RequestSigner};
+use crate::Secret;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Request,
+};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::str::FromStr;
+
+/// Generic HMAC signature over a configurable canonical string
+///
+/// The canonical string could use these placeholders: `{method}`, `{url}`, `{host}`,
+/// `{path}`, `{query}`, `{timestamp}` (unix seconds), `{date}` (RFC 3339), `{body}`,
+/// `{body_sha256}` and `{header:<name>}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct HmacSigning {
+    pub key: Secret,
+    #[serde(skip_serializing_if = "crate::config::is_default", default)]
+    pub algorithm: HmacAlgorithm,
+    /// header which carries the signature
+    pub header: String,
+    #[serde(default = "default_canonical")]
+    pub canonical: String,
+    #[serde(skip_serializing_if = "crate::config::is_default", default)]
+    pub encoding: SignatureEncoding,
+    /// prepended to the signature, e.g. `sha256=`
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub prefix: String,
+    /// header to send `{timestamp}` in, so the server could rebuild the canonical string
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timestamp_header: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HmacAlgorithm {
+    Sha1,
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+impl RequestSigner for HmacSigning {
+    fn sign_at(&self, req: &mut Request, now: DateTime<Utc>) -> Result<()> {
+        let timestamp = now.timestamp().to_string();
+        if let Some(name) = self.timestamp_header.as_deref() {
+            req.headers_mut().insert(
+                HeaderName::from_str(name)?,
+                HeaderValue::from_str(&timestamp)?,
+            );
+        }
+
+        let canonical = self.canonical_string(req, &timestamp, now)?;
+        let key = self.key.resolve()?;
+        let mac = match self.algorithm {
+            HmacAlgorithm::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes())?;
+                mac.update(canonical.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            }
+            HmacAlgorithm::Sha256 => hmac_sha256(key.as_bytes(), canonical.as_bytes()),
+            HmacAlgorithm::Sha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(key.as_bytes())?;
+                mac.update(canonical.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            }
+        };
+        let signature = match self.encoding {
+            SignatureEncoding::Hex => hex::encode(mac),
+            SignatureEncoding::Base64 => base64::encode(mac),
+        };
+
+        let mut value = HeaderValue::from_str(&format!("{}{}", self.prefix, signature))?;
+        value.set_sensitive(true);
+        req.headers_mut()
+            .insert(HeaderName::from_str(&self.header)?, value);
+        Ok(())
+    }
+}
+
+impl HmacSigning {
+    fn canonical_string(
+        &self,
+        req: &Request,
+        timestamp: &str,
+        now: DateTime<Utc>,
+    ) -> Result<String> {
+        let url = req.url();
+        let body = body_bytes(req);
+        let mut output = String::new();
+        let mut rest = self.canonical.as_str();
+
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow!("unclosed placeholder in: {}", self.canonical))?
+                + start;
+            let value = match &rest[start + 1..end] {
+                "method" => req.method().to_string(),
+                "url" => url.to_string(),
+                "host" => url.host_str().unwrap_or_default().to_string(),
+                "path" => url.path().to_string(),
+                "query" => url.query().unwrap_or_default().to_string(),
+                "timestamp" => timestamp.to_string(),
+                "date" => now.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "body" => String::from_utf8_lossy(body).to_string(),
+                "body_sha256" => hex::encode(Sha256::digest(body)),
+                v => match v.strip_prefix("header:") {
+                    Some(name) => req
+                        .headers()
+                        .get(name)
+                        .map(|v| v.to_str())
+                        .transpose()?
+                        .unwrap_or_default()
+                        .to_string(),
+                    None => return Err(anyhow!("unknown placeholder {{{}}}", v)),
+                },
+            };
+            output.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+fn default_canonical() -> String {
+    "{method}\n{path}\n{query}\n{timestamp}\n{body_sha256}".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::{Method, Url};
+
+    #[test]
+    fn hmac_signing_should_work() {
+        let signer: HmacSigning = serde_yaml::from_str(
+            r#"
+key: secret
+header: x-signature
+canonical: "{method} {path}?{query} {timestamp} {body}"
+encoding: base64
+prefix: "sha256="
+timestamp_header: x-timestamp
+"#,
+        )
+        .unwrap();
+        let mut req = Request::new(
+            Method::POST,
+            Url::parse("https://example.com/todo?a=1").unwrap(),
+        );
+        *req.body_mut() = Some("hello".into());
+        let now = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        signer.sign_at(&mut req, now).unwrap();
+
+        let expected = base64::encode(hmac_sha256(b"secret", b"POST /todo?a=1 1600000000 hello"));
+        assert_eq!(req.headers()["x-timestamp"], "1600000000");
+        assert_eq!(
+            req.headers()["x-signature"].to_str().unwrap(),
+            format!("sha256={}", expected)
+        );
+    }
+
+    #[test]
+    fn unknown_placeholder_should_fail() {
+        let signer = HmacSigning {
+            key: Secret::Value("secret".into()),
+            algorithm: HmacAlgorithm::Sha1,
+            header: "x-signature".into(),
+            canonical: "{nope}".into(),
+            encoding: SignatureEncoding::Hex,
+            prefix: String::new(),
+            timestamp_header: None,
+        };
+        let mut req = Request::new(Method::GET, Url::parse("https://example.com").unwrap());
+        assert!(signer.sign(&mut req).is_err());
+    }
+}
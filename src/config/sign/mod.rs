@@ -0,0 +1,50 @@
+mod aws_sigv4;
+mod hmac_sig;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub use aws_sigv4::AwsSigV4;
+pub use hmac_sig::{HmacAlgorithm, HmacSigning, SignatureEncoding};
+
+/// A signing stage, run on the fully generated request right before it is sent
+pub trait RequestSigner {
+    /// sign the request as of `now`, usually by adding headers
+    fn sign_at(&self, req: &mut Request, now: DateTime<Utc>) -> Result<()>;
+
+    fn sign(&self, req: &mut Request) -> Result<()> {
+        self.sign_at(req, Utc::now())
+    }
+}
+
+/// Built-in signers which could be configured from a profile
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Signing {
+    #[serde(rename = "aws_sigv4")]
+    AwsSigV4(AwsSigV4),
+    Hmac(HmacSigning),
+}
+
+impl RequestSigner for Signing {
+    fn sign_at(&self, req: &mut Request, now: DateTime<Utc>) -> Result<()> {
+        match self {
+            Signing::AwsSigV4(signer) => signer.sign_at(req, now),
+            Signing::Hmac(signer) => signer.sign_at(req, now),
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn body_bytes(req: &Request) -> &[u8] {
+    req.body().and_then(|b| b.as_bytes()).unwrap_or_default()
+}
@@ -0,0 +1,118 @@
+use super::{CookieJar, HttpVersion, Timeouts};
+use anyhow::Result;
+use hyper::{service::Service, Uri};
+use hyperlocal::UnixConnector;
+use reqwest::{
+    cookie::CookieStore,
+    header::{self, HeaderValue},
+    Request, Response, ResponseBuilderExt,
+};
+use std::{
+    future::Future,
+    io,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+type ConnectFuture =
+    Pin<Box<dyn Future<Output = io::Result<<UnixConnector as Service<Uri>>::Response>> + Send>>;
+
+/// a `UnixConnector` which gives up after the connect timeout
+#[derive(Debug, Clone, Copy)]
+struct SocketConnector(Option<Duration>);
+
+/// send a request over the unix socket at `socket`
+///
+/// there is no TLS, proxy or redirect on a socket, the cookie jar, the timeouts and the
+/// http version are the only connection settings taken into account.
+pub async fn execute(
+    socket: &Path,
+    req: Request,
+    jar: Option<&CookieJar>,
+    timeout: Option<Timeouts>,
+    http_version: Option<HttpVersion>,
+) -> Result<Response> {
+    let timeout = timeout.unwrap_or_default();
+    let send = send(socket, req, jar, timeout.connect, http_version);
+    match timeout.total {
+        Some(total) => tokio::time::timeout(total, send).await?,
+        None => send.await,
+    }
+}
+
+async fn send(
+    socket: &Path,
+    req: Request,
+    jar: Option<&CookieJar>,
+    connect: Option<Duration>,
+    http_version: Option<HttpVersion>,
+) -> Result<Response> {
+    let url = req.url().clone();
+    let mut builder =
+        hyper::Request::builder()
+            .method(req.method().clone())
+            .uri(hyperlocal::Uri::new(
+                socket,
+                &url[url::Position::BeforePath..],
+            ));
+    if let Some(headers) = builder.headers_mut() {
+        *headers = req.headers().clone();
+        if !headers.contains_key(header::HOST) {
+            let host = &url[url::Position::BeforeHost..url::Position::AfterPort];
+            headers.insert(header::HOST, HeaderValue::from_str(host)?);
+        }
+        if let Some(cookies) = jar.and_then(|jar| jar.cookies(&url)) {
+            headers.insert(header::COOKIE, cookies);
+        }
+    }
+    let body = req
+        .body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| b.to_vec())
+        .unwrap_or_default();
+    let req = builder.body(hyper::Body::from(body))?;
+
+    let client = hyper::Client::builder()
+        .http2_only(http_version == Some(HttpVersion::Http2))
+        .build::<_, hyper::Body>(SocketConnector(connect));
+    let res = client.request(req).await?;
+    if let Some(jar) = jar {
+        jar.set_cookies(&mut res.headers().get_all(header::SET_COOKIE).iter(), &url);
+    }
+
+    let (parts, body) = res.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+    let mut builder = http::Response::builder()
+        .status(parts.status)
+        .version(parts.version)
+        .url(url);
+    if let Some(headers) = builder.headers_mut() {
+        *headers = parts.headers;
+    }
+    Ok(builder.body(body)?.into())
+}
+
+impl Service<Uri> for SocketConnector {
+    type Response = <UnixConnector as Service<Uri>>::Response;
+    type Error = io::Error;
+    type Future = ConnectFuture;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connect = UnixConnector.call(uri);
+        let timeout = self.0;
+        Box::pin(async move {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, connect)
+                    .await
+                    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))?,
+                None => connect.await,
+            }
+        })
+    }
+}
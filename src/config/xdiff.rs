@@ -1,27 +1,56 @@
-use super::{is_default, ValidateConfig};
+use super::{
+    is_default, schema, BaseRewrite, Contract, ContractCache, EncodingReport, LoadedContract,
+    ProfileDefaults, ValidateConfig,
+};
 use crate::utils::diff_text;
 use crate::{ExtraArgs, LoadConfig, RequestProfile};
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DiffConfig {
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub defaults: ProfileDefaults,
     #[serde(flatten)]
     pub profiles: HashMap<String, DiffProfile>,
 }
 
 impl DiffConfig {
     pub fn new(profiles: HashMap<String, DiffProfile>) -> Self {
-        Self { profiles }
+        Self {
+            defaults: Default::default(),
+            profiles,
+        }
     }
     pub fn get_profile(&self, name: &str) -> Option<&DiffProfile> {
         self.profiles.get(name)
     }
 }
 
-impl LoadConfig for DiffConfig {}
+impl LoadConfig for DiffConfig {
+    fn apply_defaults(&mut self) {
+        // each side keeps its own session, in a jar next to the shared one
+        let side = |name: &str| {
+            let mut defaults = self.defaults.clone();
+            defaults.cookie_jar = self.defaults.cookie_jar.as_ref().map(|jar| {
+                let mut path = jar.clone().into_os_string();
+                path.push(format!(".{}", name));
+                PathBuf::from(path)
+            });
+            defaults
+        };
+        let (defaults1, defaults2) = (side("req1"), side("req2"));
+        for profile in self.profiles.values_mut() {
+            profile.req1.apply_defaults(&defaults1);
+            profile.req2.apply_defaults(&defaults2);
+        }
+    }
+}
 impl ValidateConfig for DiffConfig {
     fn validate(&self) -> Result<()> {
         for (name, profile) in &self.profiles {
@@ -39,37 +68,158 @@ pub struct DiffProfile {
     pub req2: RequestProfile,
     #[serde(skip_serializing_if = "is_default", default)]
     pub res: ResponseProfile,
+    #[serde(skip)]
+    contract: ContractCache,
 }
 
 impl DiffProfile {
     pub fn new(req1: RequestProfile, req2: RequestProfile, res: ResponseProfile) -> Self {
-        Self { req1, req2, res }
+        Self {
+            req1,
+            req2,
+            res,
+            contract: Default::default(),
+        }
+    }
+    /// the contract of `res` compiled, loaded once and shared by the clones of the profile
+    fn load_contract(&self) -> Result<Option<Arc<LoadedContract>>> {
+        let contract = match self.res.contract.as_ref() {
+            Some(contract) => contract,
+            None => return Ok(None),
+        };
+        let mut cache = self.contract.lock().unwrap();
+        if let Some((cached, loaded)) = cache.as_ref() {
+            if cached == contract {
+                return Ok(Some(loaded.clone()));
+            }
+        }
+        let loaded = Arc::new(contract.load()?);
+        *cache = Some((contract.clone(), loaded.clone()));
+        Ok(Some(loaded))
     }
     pub async fn diff(&self, args: ExtraArgs) -> Result<String> {
-        let res1 = self.req1.send(&args).await?;
-        let res2 = self.req2.send(&args).await?;
+        Ok(self.compare(&args).await?.output)
+    }
+    /// send both requests and diff their responses
+    pub async fn compare(&self, args: &ExtraArgs) -> Result<DiffResult> {
+        let contract = self.load_contract()?;
+        let res1 = self.req1.send(args).await?;
+        let res2 = self.req2.send(args).await?;
+
+        let attempts = (res1.attempts(), res2.attempts());
+        let encodings = (res1.encoding().cloned(), res2.encoding().cloned());
 
-        let text1 = res1.get_text(&self.res).await?;
-        let text2 = res2.get_text(&self.res).await?;
+        let rewriter = self
+            .res
+            .rewrite_base
+            .as_ref()
+            .map(|r| r.rewriter(&[res1.url(), res2.url()]));
+        let (text1, violations1) = res1
+            .get_checked_text(&self.res, contract.as_deref(), rewriter.as_ref())
+            .await?;
+        let (text2, violations2) = res2
+            .get_checked_text(&self.res, contract.as_deref(), rewriter.as_ref())
+            .await?;
+        let (text1, text2) = if self.res.schema_only {
+            schema::fill_unknown(&text1, &text2)
+        } else {
+            (text1, text2)
+        };
 
-        diff_text(&text1, &text2)
+        let mut output = diff_text(&text1, &text2)?;
+        if attempts.0 > 1 || attempts.1 > 1 {
+            writeln!(
+                &mut output,
+                "\nattempts: req1 {}, req2 {}",
+                attempts.0, attempts.1
+            )?;
+        }
+        if encodings.0.is_some() || encodings.1.is_some() {
+            let text = |e: Option<EncodingReport>| e.map_or_else(|| "-".into(), |e| e.to_string());
+            writeln!(
+                &mut output,
+                "encoding: req1 {}, req2 {}",
+                text(encodings.0),
+                text(encodings.1)
+            )?;
+        }
+        if contract.is_some() {
+            let text = |v: &[String]| match v.len() {
+                0 => "ok".to_string(),
+                1 => "1 violation".to_string(),
+                n => format!("{} violations", n),
+            };
+            writeln!(
+                &mut output,
+                "contract: req1 {}, req2 {}",
+                text(&violations1),
+                text(&violations2)
+            )?;
+            for (name, violations) in [("req1", &violations1), ("req2", &violations2)] {
+                for violation in violations {
+                    writeln!(&mut output, "  {}: {}", name, violation)?;
+                }
+            }
+        }
+        Ok(DiffResult {
+            matched: text1 == text2,
+            output,
+            violations: (violations1, violations2),
+        })
     }
 }
 
+/// The diff of the two responses of a profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffResult {
+    /// both responses are the same once the skipped parts are left out
+    pub matched: bool,
+    pub output: String,
+    /// how req1 and req2 broke the contract of the profile, if it has one
+    pub violations: (Vec<String>, Vec<String>),
+}
+
 impl ValidateConfig for DiffProfile {
     fn validate(&self) -> Result<()> {
         self.req1.validate().context("req1 failed to validate")?;
         self.req2.validate().context("req2 failed to validate")?;
+        if self.req1.cookie_jar.is_some() && self.req1.cookie_jar == self.req2.cookie_jar {
+            return Err(anyhow!("req1 and req2 need their own cookie_jar"));
+        }
+        self.load_contract().context("contract failed to load")?;
         Ok(())
     }
 }
 
+/// left out of snapshots, they differ between two responses of the same content
+const VOLATILE_HEADERS: &[&str] = &[
+    "age",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "set-cookie",
+    "x-request-id",
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub struct ResponseProfile {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_headers: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub skip_body: Vec<String>,
+    /// leave the protocol version (e.g. `HTTP/2.0`) out of the status line
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub skip_version: bool,
+    /// compare the shape of json bodies (fields, types, nullability) instead of their values
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub schema_only: bool,
+    /// the schema each response should keep to, checked apart from the diff
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub contract: Option<Contract>,
+    /// replace the base urls of req1 and req2 in the responses before comparing them
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rewrite_base: Option<BaseRewrite>,
 }
 
 impl ResponseProfile {
@@ -77,6 +227,72 @@ impl ResponseProfile {
         Self {
             skip_headers,
             skip_body,
+            skip_version: false,
+            schema_only: false,
+            contract: None,
+            rewrite_base: None,
         }
     }
+    /// how recorded responses are kept as snapshots, without the protocol version as
+    /// captures are often over h2, nor the headers which change on every response
+    pub fn snapshot() -> Self {
+        Self {
+            skip_headers: VOLATILE_HEADERS.iter().map(|h| h.to_string()).collect(),
+            skip_version: true,
+            ..Default::default()
+        }
+    }
+    /// a snapshot recorded before with the headers of `skip_headers` left out, as if it
+    /// was recorded with this profile
+    ///
+    /// the header lines run from the status line up to the first empty line, and the
+    /// first header is on the status line itself.
+    pub fn skip_recorded_headers(&self, text: &str) -> String {
+        let skipped = |line: &str| {
+            self.skip_headers.iter().any(|h| {
+                line.strip_prefix(h.as_str())
+                    .is_some_and(|rest| rest.starts_with(": \""))
+            })
+        };
+        let mut output = String::new();
+        let mut lines = text.split_inclusive('\n');
+        for line in lines.by_ref() {
+            if line.starts_with("redirect: ") {
+                output.push_str(line);
+                continue;
+            }
+            // e.g. `200 OKdate: "..."`
+            let status = status_len(line);
+            match skipped(&line[status..]) {
+                true => output.push_str(&line[..status]),
+                false => output.push_str(line),
+            }
+            break;
+        }
+        for line in lines.by_ref() {
+            if !skipped(line) {
+                output.push_str(line);
+            }
+            if line == "\n" {
+                break;
+            }
+        }
+        output.extend(lines);
+        output
+    }
+}
+
+/// the length of the status at the start of a snapshot line, like `404 Not Found`
+fn status_len(line: &str) -> usize {
+    let code = line
+        .split(' ')
+        .find_map(|part| StatusCode::from_bytes(part.as_bytes()).ok());
+    let status = match code {
+        Some(code) => code.to_string(),
+        None => return line.len(),
+    };
+    match line.find(&status) {
+        Some(pos) => pos + status.len(),
+        None => line.len(),
+    }
 }
@@ -3,24 +3,35 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::ValidateConfig;
+use super::{is_default, ProfileDefaults, ValidateConfig};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RequestConfig {
+    #[serde(skip_serializing_if = "is_default", default)]
+    pub defaults: ProfileDefaults,
     #[serde(flatten)]
     pub profiles: HashMap<String, RequestProfile>,
 }
 
 impl RequestConfig {
     pub fn new(profiles: HashMap<String, RequestProfile>) -> Self {
-        Self { profiles }
+        Self {
+            defaults: Default::default(),
+            profiles,
+        }
     }
     pub fn get_profile(&self, name: &str) -> Option<&RequestProfile> {
         self.profiles.get(name)
     }
 }
 
-impl LoadConfig for RequestConfig {}
+impl LoadConfig for RequestConfig {
+    fn apply_defaults(&mut self) {
+        for profile in self.profiles.values_mut() {
+            profile.apply_defaults(&self.defaults);
+        }
+    }
+}
 impl ValidateConfig for RequestConfig {
     fn validate(&self) -> Result<()> {
         for (name, profile) in &self.profiles {
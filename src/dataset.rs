@@ -0,0 +1,231 @@
+use crate::{
+    cli::{parse_key_val, KeyVal},
+    DiffProfile, DiffResult, ExtraArgs,
+};
+use anyhow::{anyhow, Context, Result};
+use futures::{stream, StreamExt};
+use std::{fmt::Write as _, path::Path};
+
+/// Rows of overrides to run a profile with, the columns are named like the `-e` keys
+///
+/// e.g. `:user_id` for a path param, `status` for a query param, `%x-tenant` for a header
+/// and `@title` for a body field. Empty csv cells and json nulls are left out.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Dataset {
+    pub rows: Vec<ExtraArgs>,
+}
+
+/// How a row of a dataset went
+#[derive(Debug)]
+pub struct RowResult {
+    /// starting from 1
+    pub row: usize,
+    pub result: Result<DiffResult>,
+}
+
+impl Dataset {
+    /// load a `.json` file (an array of objects) or a csv file with a header
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read dataset {}", path.display()))?;
+        match path.extension().and_then(|v| v.to_str()) {
+            Some("json") => Self::from_json(&content),
+            _ => Self::from_csv(&content),
+        }
+    }
+
+    pub fn from_csv(content: &str) -> Result<Self> {
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        let columns = reader.headers()?.clone();
+        let mut rows = vec![];
+        for record in reader.records() {
+            let record = record?;
+            let cells = columns.iter().zip(record.iter());
+            rows.push(to_extra_args(cells.map(|(k, v)| (k, v.to_string())))?);
+        }
+        Ok(Self { rows })
+    }
+
+    /// `@` fields keep their json type, the other values are used as text
+    pub fn from_json(content: &str) -> Result<Self> {
+        let values: Vec<serde_json::Map<String, serde_json::Value>> =
+            serde_json::from_str(content).context("dataset must be an array of objects")?;
+        let rows = values
+            .iter()
+            .map(|obj| {
+                let (body, cells): (Vec<_>, Vec<_>) = obj
+                    .iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .partition(|(k, _)| k.trim().starts_with('@') && k.trim() != "@-");
+                let mut args = to_extra_args(cells.into_iter().map(|(k, v)| {
+                    let v = match v {
+                        serde_json::Value::String(s) => s.clone(),
+                        v => v.to_string(),
+                    };
+                    (k.as_str(), v)
+                }))?;
+                args.body = body
+                    .into_iter()
+                    .map(|(k, v)| (k.trim()[1..].to_string(), v.clone()))
+                    .collect();
+                Ok(args)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rows })
+    }
+
+    /// diff the profile once per row, at most `concurrency` rows at a time
+    ///
+    /// `args` are applied first, so the row could override them.
+    pub async fn run(
+        &self,
+        profile: &DiffProfile,
+        args: &ExtraArgs,
+        concurrency: usize,
+    ) -> Vec<RowResult> {
+        stream::iter(self.rows.iter().enumerate())
+            .map(|(idx, row)| async move {
+                let args = args.merge(row);
+                RowResult {
+                    row: idx + 1,
+                    result: profile.compare(&args).await,
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+}
+
+/// a summary of the rows, with the diffs of the differing rows if `show_diffs`
+pub fn summarize_rows(results: &[RowResult], show_diffs: bool) -> Result<String> {
+    let mut output = String::new();
+    let mut differed = vec![];
+    let mut failed = vec![];
+    let mut violated = vec![];
+    for res in results {
+        match &res.result {
+            Ok(diff) => {
+                let broke = !diff.violations.0.is_empty() || !diff.violations.1.is_empty();
+                if broke {
+                    violated.push(res.row.to_string());
+                }
+                if !diff.matched {
+                    differed.push(res.row.to_string());
+                }
+                if show_diffs && (broke || !diff.matched) {
+                    write!(&mut output, "--- row {}\n{}", res.row, diff.output)?;
+                }
+            }
+            Err(e) => failed.push(format!("row {}: {:#}", res.row, e)),
+        }
+    }
+    writeln!(
+        &mut output,
+        "rows: {}, matched: {}, differed: {}, failed: {}",
+        results.len(),
+        results.len() - differed.len() - failed.len(),
+        differed.len(),
+        failed.len()
+    )?;
+    if !differed.is_empty() {
+        writeln!(&mut output, "differed rows: {}", differed.join(", "))?;
+    }
+    if !violated.is_empty() {
+        writeln!(
+            &mut output,
+            "contract violated rows: {}",
+            violated.join(", ")
+        )?;
+    }
+    for line in failed {
+        writeln!(&mut output, "{}", line)?;
+    }
+    Ok(output)
+}
+
+fn to_extra_args<'a>(cells: impl Iterator<Item = (&'a str, String)>) -> Result<ExtraArgs> {
+    let key_vals = cells
+        .filter(|(_, v)| !v.is_empty())
+        .map(|(k, v)| {
+            if k.trim() == "@-" {
+                return Err(anyhow!("stdin body can't be used in a dataset"));
+            }
+            parse_key_val(&format!("{}={}", k, v))
+        })
+        .collect::<Result<Vec<KeyVal>>>()?;
+    key_vals.try_into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RequestProfile, ResponseProfile};
+    use mockito::mock;
+
+    #[test]
+    fn dataset_from_csv_should_work() {
+        let dataset = Dataset::from_csv(":user_id,status,%x-tenant\n42,open,\n43,,acme\n").unwrap();
+        assert_eq!(
+            dataset.rows,
+            vec![
+                ExtraArgs {
+                    path: vec![("user_id".into(), "42".into())],
+                    query: vec![("status".into(), "open".into())],
+                    ..Default::default()
+                },
+                ExtraArgs {
+                    path: vec![("user_id".into(), "43".into())],
+                    headers: vec![("x-tenant".into(), "acme".into())],
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dataset_from_json_should_work() {
+        let dataset = Dataset::from_json(
+            r#"[{":user_id": 42, "@done": true, "@code": "007", "@tags": ["a"], "@note": null}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            dataset.rows,
+            vec![ExtraArgs {
+                path: vec![("user_id".into(), "42".into())],
+                body: vec![
+                    ("code".into(), serde_json::json!("007")),
+                    ("done".into(), serde_json::json!(true)),
+                    ("tags".into(), serde_json::json!(["a"])),
+                ],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn dataset_run_should_work() {
+        let _m1 = mock("GET", "/dataset/1").with_body("same").create();
+        let _m2 = mock("GET", "/dataset/2").with_body("old").create();
+        let _m3 = mock("GET", "/dataset-new/1").with_body("same").create();
+        let _m4 = mock("GET", "/dataset-new/2").with_body("new").create();
+
+        let url = mockito::server_url();
+        let req1: RequestProfile = format!("{}/dataset/{{id}}", url).parse().unwrap();
+        let req2: RequestProfile = format!("{}/dataset-new/{{id}}", url).parse().unwrap();
+        let profile = DiffProfile::new(
+            req1,
+            req2,
+            ResponseProfile::new(vec!["date".into()], vec![]),
+        );
+        let dataset = Dataset::from_csv(":id\n1\n2\n").unwrap();
+
+        let results = dataset.run(&profile, &Default::default(), 2).await;
+        let output = summarize_rows(&results, false).unwrap();
+        assert_eq!(
+            output,
+            "rows: 2, matched: 1, differed: 1, failed: 0\ndiffered rows: 2\n"
+        );
+    }
+}
@@ -1,18 +1,31 @@
 pub mod cli;
 mod config;
+mod dataset;
 mod utils;
 
 pub use config::{
-    get_body_text, get_header_text, get_status_text, DiffConfig, DiffProfile, LoadConfig,
-    RequestConfig, RequestProfile, ResponseProfile,
+    get_body_text, get_header_text, get_redirect_text, get_schema_text, get_status_text,
+    import_requests, lookup_json, parse_curl, parse_har, parse_openapi, parse_postman, rebase,
+    Auth, AwsSigV4, BaseRewrite, Capture, Compression, Contract, CookieJar, DiffConfig,
+    DiffProfile, DiffResult, Encoding, EncodingReport, HarFilter, HmacAlgorithm, HmacSigning,
+    HttpVersion, Imported, ImportedRequest, LoadConfig, OAuth2, ProfileDefaults, QueryStyle,
+    Redirect, RedirectHop, RedirectMode, RequestConfig, RequestProfile, RequestSigner,
+    ResponseProfile, RetryPolicy, Secret, SetupStep, SignatureEncoding, Signing, Timeouts,
+    Transport, Vars,
 };
+pub use dataset::{summarize_rows, Dataset, RowResult};
 pub use utils::{diff_text, highlight_text, process_error_output};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ExtraArgs {
     pub headers: Vec<(String, String)>,
     pub query: Vec<(String, String)>,
-    pub body: Vec<(String, String)>,
+    /// body fields, typed like json values
+    pub body: Vec<(String, serde_json::Value)>,
+    /// values for the `{name}` placeholders in the url path
+    pub path: Vec<(String, String)>,
+    /// a body sent verbatim, e.g. read from stdin
+    pub body_raw: Option<Vec<u8>>,
 }
 impl ExtraArgs {
     pub fn new_with_headers(headers: Vec<(String, String)>) -> Self {
@@ -29,10 +42,31 @@ impl ExtraArgs {
         }
     }
 
-    pub fn new_with_body(body: Vec<(String, String)>) -> Self {
+    pub fn new_with_body(body: Vec<(String, serde_json::Value)>) -> Self {
         Self {
             body,
             ..Default::default()
         }
     }
-}
\ No newline at end of file
+
+    pub fn new_with_path(path: Vec<(String, String)>) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// these args followed by `other`, so `other` wins where both set a value
+    pub fn merge(&self, other: &ExtraArgs) -> Self {
+        fn concat<T: Clone>(a: &[(String, T)], b: &[(String, T)]) -> Vec<(String, T)> {
+            [a, b].concat()
+        }
+        Self {
+            headers: concat(&self.headers, &other.headers),
+            query: concat(&self.query, &other.query),
+            body: concat(&self.body, &other.body),
+            path: concat(&self.path, &other.path),
+            body_raw: other.body_raw.clone().or_else(|| self.body_raw.clone()),
+        }
+    }
+}